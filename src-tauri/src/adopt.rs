@@ -0,0 +1,183 @@
+use anyhow::{Result, anyhow};
+use chrono::NaiveDate;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+use crate::config::{AdoptRename, FolderScan};
+
+/// 扫描时跳过的目录
+const SKIPPED_DIRS: [&str; 3] = [".git", ".desktop_data", "node_modules"];
+
+/// 扫描文件夹中的 Markdown 文件，按日文件结构分类
+pub fn scan_folder(root: &str) -> Result<FolderScan> {
+    let root_path = Path::new(root);
+    if !root_path.is_dir() {
+        return Err(anyhow!("目录 {} 不存在", root));
+    }
+
+    let mut files = vec![];
+    collect_markdown(root_path, root_path, &mut files)?;
+    files.sort();
+
+    let mut scan = FolderScan {
+        is_git_repo: root_path.join(".git").exists(),
+        ..Default::default()
+    };
+    collect_attachments(root_path, &mut scan.attachments)?;
+    scan.attachments.sort();
+    let mut targets: HashMap<String, Vec<String>> = HashMap::new();
+
+    for path in &files {
+        if is_day_file(path) {
+            scan.matched.push(path.clone());
+        } else if let Some(date) = near_match_date(path) {
+            targets.entry(day_file_path(date)).or_default().push(path.clone());
+        } else {
+            scan.unmatched.push(path.clone());
+        }
+    }
+
+    let mut sorted_targets: Vec<(String, Vec<String>)> = targets.into_iter().collect();
+    sorted_targets.sort();
+
+    for (to, sources) in sorted_targets {
+        // 目标已存在或同一天有多个来源时不自动转换
+        let conflict = sources.len() > 1 || scan.matched.contains(&to) || root_path.join(&to).exists();
+        for from in sources {
+            let rename = AdoptRename { from, to: to.clone() };
+            if conflict {
+                scan.conflicts.push(rename);
+            } else {
+                scan.convertible.push(rename);
+            }
+        }
+    }
+
+    Ok(scan)
+}
+
+/// 将可转换的文件移动到日文件位置，返回实际移动的文件
+pub fn convert_near_matches(root: &str, scan: &FolderScan) -> Result<Vec<AdoptRename>> {
+    let root_path = Path::new(root);
+    let mut renamed = vec![];
+
+    for rename in &scan.convertible {
+        let from = root_path.join(&rename.from);
+        let to = root_path.join(&rename.to);
+        if to.exists() {
+            continue;
+        }
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(&from, &to)?;
+        renamed.push(rename.clone());
+    }
+
+    Ok(renamed)
+}
+
+/// 导入提交包含的文件：扫描到的 Markdown 文件（已转换的使用新位置）和附件，目录中的其他文件不导入
+pub fn import_paths(scan: &FolderScan, renamed: &[AdoptRename]) -> Vec<String> {
+    let mut paths: Vec<String> = scan.matched.iter().chain(&scan.unmatched).chain(&scan.attachments).cloned().collect();
+    for rename in scan.convertible.iter().chain(&scan.conflicts) {
+        match renamed.iter().find(|r| r.from == rename.from) {
+            Some(r) => paths.push(r.to.clone()),
+            None => paths.push(rename.from.clone()),
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+fn collect_markdown(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if path.is_dir() {
+            if !SKIPPED_DIRS.contains(&name.as_str()) {
+                collect_markdown(root, &path, files)?;
+            }
+        } else if name.to_lowercase().ends_with(".md") {
+            let relative = path.strip_prefix(root)?.to_string_lossy().replace('\\', "/");
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// 日文件引用的附件：YYYY/MM/assets/ 下的文件
+fn collect_attachments(root: &Path, files: &mut Vec<String>) -> Result<()> {
+    let is_number = |name: &str, len: usize| name.len() == len && name.chars().all(|c| c.is_ascii_digit());
+
+    for year in std::fs::read_dir(root)?.flatten() {
+        let year_name = year.file_name().to_string_lossy().to_string();
+        if !is_number(&year_name, 4) || !year.path().is_dir() {
+            continue;
+        }
+        for month in std::fs::read_dir(year.path())?.flatten() {
+            let month_name = month.file_name().to_string_lossy().to_string();
+            let assets = month.path().join("assets");
+            if !is_number(&month_name, 2) || !assets.is_dir() {
+                continue;
+            }
+            for file in std::fs::read_dir(&assets)?.flatten() {
+                if file.path().is_file() {
+                    let name = file.file_name().to_string_lossy().to_string();
+                    files.push(format!("{}/{}/assets/{}", year_name, month_name, name));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 是否符合 YYYY/MM/MM-DD.md（月份一致且日期有效）
+pub fn is_day_file(path: &str) -> bool {
+    let day_regex = Regex::new(r"^(\d{4})/(\d{2})/(\d{2})-(\d{2})\.md$").unwrap();
+    match day_regex.captures(path) {
+        Some(caps) => caps[2] == caps[3] && parse_date(&caps[1], &caps[3], &caps[4]).is_some(),
+        None => false,
+    }
+}
+
+/// 从近似的文件名/路径中识别日期：
+/// 文件名包含完整日期（2026-10-17.md、2026_10_17.md、2026.10.17.md、20261017.md），
+/// 或由上级目录给出年月（2026/10/17.md、2026/10-17.md、2026/10/10-17.md 放错位置等）
+fn near_match_date(path: &str) -> Option<NaiveDate> {
+    let parts: Vec<&str> = path.split('/').collect();
+    let name = parts.last()?.strip_suffix(".md").or_else(|| parts.last()?.strip_suffix(".MD"))?;
+
+    let full_date = Regex::new(r"^(\d{4})[-_.]?(\d{2})[-_.]?(\d{2})$").unwrap();
+    if let Some(caps) = full_date.captures(name) {
+        return parse_date(&caps[1], &caps[2], &caps[3]);
+    }
+
+    let year_dir = parts.iter().rev().skip(1).find(|p| p.len() == 4 && p.chars().all(|c| c.is_ascii_digit()))?;
+
+    let month_day = Regex::new(r"^(\d{1,2})[-_.](\d{1,2})$").unwrap();
+    if let Some(caps) = month_day.captures(name) {
+        return parse_date(year_dir, &caps[1], &caps[2]);
+    }
+
+    // 年/月/日.md
+    let day_only = Regex::new(r"^\d{1,2}$").unwrap();
+    if day_only.is_match(name) && parts.len() >= 3 {
+        let month_dir = parts[parts.len() - 2];
+        if month_dir.len() <= 2 && month_dir.chars().all(|c| c.is_ascii_digit()) {
+            return parse_date(year_dir, month_dir, name);
+        }
+    }
+
+    None
+}
+
+fn parse_date(year: &str, month: &str, day: &str) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+}
+
+fn day_file_path(date: NaiveDate) -> String {
+    date.format("%Y/%m/%m-%d.md").to_string()
+}
@@ -0,0 +1,367 @@
+use chrono::Local;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use crate::config::Config;
+
+/// 产生提交的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitAction {
+    Update,
+    UploadAttachment,
+    DeleteAttachment,
+    DeleteTask,
+    RestoreFile,
+    RestoreTask,
+    ResolveConflicts,
+    Merge,
+    Import,
+    Init,
+    Gitignore,
+    UntrackDesktopData,
+    PublishPages,
+}
+
+impl CommitAction {
+    pub const ALL: [CommitAction; 13] = [
+        CommitAction::Update,
+        CommitAction::UploadAttachment,
+        CommitAction::DeleteAttachment,
+        CommitAction::DeleteTask,
+        CommitAction::RestoreFile,
+        CommitAction::RestoreTask,
+        CommitAction::ResolveConflicts,
+        CommitAction::Merge,
+        CommitAction::Import,
+        CommitAction::Init,
+        CommitAction::Gitignore,
+        CommitAction::UntrackDesktopData,
+        CommitAction::PublishPages,
+    ];
+
+    /// 模板覆盖和 {action} 占位符使用的操作名
+    pub fn key(self) -> &'static str {
+        match self {
+            CommitAction::Update => "update",
+            CommitAction::UploadAttachment => "upload_attachment",
+            CommitAction::DeleteAttachment => "delete_attachment",
+            CommitAction::DeleteTask => "delete_task",
+            CommitAction::RestoreFile => "restore_file",
+            CommitAction::RestoreTask => "restore_task",
+            CommitAction::ResolveConflicts => "resolve_conflicts",
+            CommitAction::Merge => "merge",
+            CommitAction::Import => "import",
+            CommitAction::Init => "init",
+            CommitAction::Gitignore => "gitignore",
+            CommitAction::UntrackDesktopData => "untrack_desktop_data",
+            CommitAction::PublishPages => "publish_pages",
+        }
+    }
+
+    /// 内置模板: zh（默认）, en, conventional
+    fn builtin(self, style: &str) -> &'static str {
+        match style {
+            "en" => match self {
+                CommitAction::Update => "Update {file}",
+                CommitAction::UploadAttachment => "Upload attachment {file}",
+                CommitAction::DeleteAttachment => "Delete attachment {file}",
+                CommitAction::DeleteTask => "Delete past task: {task}",
+                CommitAction::RestoreFile => "Restore {file} to {commit}",
+                CommitAction::RestoreTask => "Restore task: {task}",
+                CommitAction::ResolveConflicts => "Resolve merge conflicts",
+                CommitAction::Merge => "Merge remote changes",
+                CommitAction::Import => "Import existing notes ({count} day files, {renamed} renamed)",
+                CommitAction::Init => "Initialize repository",
+                CommitAction::Gitignore => "Add .desktop_data to .gitignore",
+                CommitAction::UntrackDesktopData => "Stop tracking .desktop_data (local client data is no longer synced)",
+                CommitAction::PublishPages => "Publish site ({commit})",
+            },
+            "conventional" => match self {
+                CommitAction::Update => "docs(todo): update {file}",
+                CommitAction::UploadAttachment => "feat(attachments): add {file}",
+                CommitAction::DeleteAttachment => "chore(attachments): remove {file}",
+                CommitAction::DeleteTask => "docs(todo): remove past task \"{task}\"",
+                CommitAction::RestoreFile => "revert(todo): restore {file} to {commit}",
+                CommitAction::RestoreTask => "docs(todo): restore task \"{task}\"",
+                CommitAction::ResolveConflicts => "chore(sync): resolve merge conflicts",
+                CommitAction::Merge => "chore(sync): merge remote changes",
+                CommitAction::Import => "chore: import existing notes ({count} day files, {renamed} renamed)",
+                CommitAction::Init => "chore: initialize repository",
+                CommitAction::Gitignore => "chore: ignore .desktop_data",
+                CommitAction::UntrackDesktopData => "chore: stop tracking .desktop_data",
+                CommitAction::PublishPages => "chore(pages): publish site ({commit})",
+            },
+            _ => match self {
+                CommitAction::Update => "更新 {file}",
+                CommitAction::UploadAttachment => "上传附件 {file}",
+                CommitAction::DeleteAttachment => "删除附件 {file}",
+                CommitAction::DeleteTask => "删除往期任务: {task}",
+                CommitAction::RestoreFile => "恢复 {file} 到 {commit}",
+                CommitAction::RestoreTask => "恢复任务: {task}",
+                CommitAction::ResolveConflicts => "解决合并冲突",
+                CommitAction::Merge => "合并远程更改",
+                CommitAction::Import => "导入现有笔记（{count} 个日文件，转换 {renamed} 个）",
+                CommitAction::Init => "初始化仓库",
+                CommitAction::Gitignore => "添加 .desktop_data 到 .gitignore",
+                CommitAction::UntrackDesktopData => "停止跟踪 .desktop_data（本地客户端数据不再同步）",
+                CommitAction::PublishPages => "发布站点 ({commit})",
+            },
+        }
+    }
+}
+
+/// 模板占位符的取值，未提供的占位符替换为空
+#[derive(Debug, Default)]
+pub struct MessageVars<'a> {
+    pub file: Option<&'a str>,
+    pub task: Option<&'a str>,
+    pub commit: Option<&'a str>,
+    pub count: Option<usize>,
+    /// 导入时转换了文件名的文件数
+    pub renamed: Option<usize>,
+}
+
+impl<'a> MessageVars<'a> {
+    pub fn file(file: &'a str) -> Self {
+        Self { file: Some(file), ..Default::default() }
+    }
+
+    pub fn task(task: &'a str) -> Self {
+        Self { task: Some(task), ..Default::default() }
+    }
+}
+
+/// 生成提交信息：优先使用配置中的覆盖模板，否则使用所选风格的内置模板
+///
+/// 支持的占位符: {date} {time} {file} {action} {task} {device} {count} {renamed} {commit}
+pub fn render(config: &Config, action: CommitAction, vars: &MessageVars) -> String {
+    let templates = &config.commit_templates;
+    let builtin = action.builtin(&templates.style);
+    let template = templates
+        .overrides
+        .get(action.key())
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty())
+        .unwrap_or(builtin);
+
+    let message = fill(template, config, action, vars);
+    if message.trim().is_empty() {
+        // 自定义模板的占位符全部为空时退回内置模板，避免空提交信息
+        fill(builtin, config, action, vars)
+    } else {
+        message
+    }
+}
+
+fn fill(template: &str, config: &Config, action: CommitAction, vars: &MessageVars) -> String {
+    let now = Local::now();
+    let count = vars.count.map(|c| c.to_string()).unwrap_or_default();
+    let renamed = vars.renamed.map(|c| c.to_string()).unwrap_or_default();
+    // 读取主机名有开销，只在模板使用 {device} 时获取
+    let device = if template.contains("{device}") { device_name(config) } else { String::new() };
+
+    // 一次替换所有占位符，任务名、文件名中的 "{...}" 不会被再次替换；未知占位符原样保留
+    let placeholder = Regex::new(r"\{(\w+)\}").unwrap();
+    placeholder
+        .replace_all(template, |caps: &Captures| match &caps[1] {
+            "date" => now.format("%Y-%m-%d").to_string(),
+            "time" => now.format("%H:%M").to_string(),
+            "file" => vars.file.unwrap_or("").to_string(),
+            "action" => action.key().to_string(),
+            "task" => vars.task.unwrap_or("").to_string(),
+            "device" => device.clone(),
+            "count" => count.clone(),
+            "renamed" => renamed.clone(),
+            "commit" => vars.commit.unwrap_or("").to_string(),
+            _ => caps[0].to_string(),
+        })
+        .trim()
+        .to_string()
+}
+
+/// 所选风格的内置模板（操作名 → 模板），供设置页展示
+pub fn builtin_templates(style: &str) -> HashMap<String, String> {
+    CommitAction::ALL
+        .iter()
+        .map(|action| (action.key().to_string(), action.builtin(style).to_string()))
+        .collect()
+}
+
+/// 记录提交设备的 trailer 键
+pub const DEVICE_TRAILER: &str = "Device";
+
+/// 当前设备名称（未配置时使用主机名）
+pub fn device_name(config: &Config) -> String {
+    let name = config.device_name.trim();
+    if name.is_empty() {
+        hostname()
+    } else {
+        name.to_string()
+    }
+}
+
+/// 在提交信息末尾追加 "Device: <设备名>" trailer（已有时不重复添加）
+pub fn with_device_trailer(config: &Config, message: &str) -> String {
+    let message = message.trim_end();
+    if device_from_message(message).is_some() {
+        return format!("{}\n", message);
+    }
+
+    let trailer = format!("{}: {}", DEVICE_TRAILER, device_name(config));
+    // 最后一段已经是 trailer（如 Signed-off-by）时接在其后，否则另起一段
+    let last_paragraph = message.rsplit("\n\n").next().unwrap_or("");
+    let has_trailers = message.contains("\n\n")
+        && last_paragraph.lines().all(is_trailer_line);
+
+    if has_trailers {
+        format!("{}\n{}\n", message, trailer)
+    } else {
+        format!("{}\n\n{}\n", message, trailer)
+    }
+}
+
+/// 从提交信息中读取 Device trailer
+pub fn device_from_message(message: &str) -> Option<String> {
+    let last_paragraph = message.trim_end().rsplit("\n\n").next()?;
+    if !message.trim_end().contains("\n\n") {
+        return None;
+    }
+    last_paragraph.lines().rev().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim().eq_ignore_ascii_case(DEVICE_TRAILER) && !value.trim().is_empty())
+            .then(|| value.trim().to_string())
+    })
+}
+
+fn is_trailer_line(line: &str) -> bool {
+    line.split_once(": ").is_some_and(|(key, _)| {
+        !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+pub fn hostname() -> String {
+    let from_env = std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok();
+    let from_file = || {
+        std::fs::read_to_string("/etc/hostname")
+            .ok()
+            .map(|s| s.trim().to_string())
+    };
+    let from_command = || {
+        std::process::Command::new("hostname")
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+    };
+
+    from_env
+        .filter(|s| !s.trim().is_empty())
+        .or_else(|| from_file().filter(|s| !s.is_empty()))
+        .or_else(|| from_command().filter(|s| !s.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CommitTemplates;
+
+    fn config(style: &str) -> Config {
+        Config {
+            device_name: "laptop".to_string(),
+            commit_templates: CommitTemplates { style: style.to_string(), ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn render_uses_builtin_styles() {
+        let vars = MessageVars::task("写周报");
+        assert_eq!(render(&config("zh"), CommitAction::DeleteTask, &vars), "删除往期任务: 写周报");
+        assert_eq!(render(&config("en"), CommitAction::DeleteTask, &vars), "Delete past task: 写周报");
+        assert_eq!(
+            render(&config("conventional"), CommitAction::DeleteTask, &vars),
+            "docs(todo): remove past task \"写周报\""
+        );
+
+        let vars = MessageVars { count: Some(3), renamed: Some(1), ..Default::default() };
+        assert_eq!(render(&config("zh"), CommitAction::Import, &vars), "导入现有笔记（3 个日文件，转换 1 个）");
+    }
+
+    #[test]
+    fn render_falls_back_to_builtin() {
+        let mut config = config("en");
+        let vars = MessageVars::file("2026/10/18.md");
+
+        // 空白覆盖模板被忽略
+        config.commit_templates.overrides.insert("update".to_string(), "  ".to_string());
+        assert_eq!(render(&config, CommitAction::Update, &vars), "Update 2026/10/18.md");
+
+        config.commit_templates.overrides.insert("update".to_string(), "[{device}] {action} {file}".to_string());
+        assert_eq!(render(&config, CommitAction::Update, &vars), "[laptop] update 2026/10/18.md");
+
+        // 占位符全部为空时退回内置模板
+        config.commit_templates.overrides.insert("update".to_string(), "{task}".to_string());
+        assert_eq!(render(&config, CommitAction::Update, &vars), "Update 2026/10/18.md");
+    }
+
+    #[test]
+    fn render_does_not_substitute_inside_values() {
+        let mut config = config("zh");
+        config.commit_templates.overrides.insert("delete_task".to_string(), "{task} ({file}) {unknown}".to_string());
+        let vars = MessageVars { task: Some("改 {file} 和 {device}"), file: Some("{task}.md"), ..Default::default() };
+        assert_eq!(
+            render(&config, CommitAction::DeleteTask, &vars),
+            "改 {file} 和 {device} ({task}.md) {unknown}"
+        );
+    }
+
+    #[test]
+    fn device_trailer_joins_existing_trailers() {
+        let message = "Update 2026/10/18.md\n\nSigned-off-by: Alice <alice@example.com>\n";
+        assert_eq!(
+            with_device_trailer(&config("zh"), message),
+            "Update 2026/10/18.md\n\nSigned-off-by: Alice <alice@example.com>\nDevice: laptop\n"
+        );
+    }
+
+    #[test]
+    fn device_trailer_starts_new_paragraph() {
+        // 单行提交信息
+        let message = with_device_trailer(&config("zh"), "Update 2026/10/18.md");
+        assert_eq!(message, "Update 2026/10/18.md\n\nDevice: laptop\n");
+        assert_eq!(device_from_message(&message).as_deref(), Some("laptop"));
+
+        // 最后一段是正文而不是 trailer
+        let message = with_device_trailer(&config("zh"), "Update\n\nSigned-off-by: Alice\n\nWhy it changed: see notes");
+        assert_eq!(message, "Update\n\nSigned-off-by: Alice\n\nWhy it changed: see notes\n\nDevice: laptop\n");
+    }
+
+    #[test]
+    fn device_trailer_is_not_duplicated() {
+        let message = "Update\n\ndevice: desktop\n";
+        assert_eq!(device_from_message(message).as_deref(), Some("desktop"));
+        assert_eq!(with_device_trailer(&config("zh"), message), "Update\n\ndevice: desktop\n");
+
+        let once = with_device_trailer(&config("zh"), "Update");
+        assert_eq!(with_device_trailer(&config("zh"), &once), once);
+    }
+
+    #[test]
+    fn device_is_read_only_from_trailers() {
+        assert_eq!(device_from_message("Device: laptop"), None);
+        assert_eq!(device_from_message("Update\n\nDevice:  \n"), None);
+        assert_eq!(device_from_message("Update\n\nDevice: a\n\nbody"), None);
+    }
+
+    #[test]
+    fn trailer_lines() {
+        assert!(is_trailer_line("Signed-off-by: Alice <alice@example.com>"));
+        assert!(is_trailer_line("Device: laptop"));
+        assert!(!is_trailer_line("Why it changed: see notes"));
+        assert!(!is_trailer_line(": value"));
+        assert!(!is_trailer_line("Device:laptop"));
+        assert!(!is_trailer_line("plain text"));
+    }
+}
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -7,10 +7,127 @@ pub struct Config {
     pub local_path: String,
     pub user_name: String,
     pub user_email: String,
+    /// 设备名称，作为提交 trailer 记录（默认为主机名）
+    #[serde(default = "default_device_name")]
+    pub device_name: String,
     pub remote_url: Option<String>,
+    /// 仅本地模式：不拉取/推送，所有同步入口停用
+    #[serde(default)]
+    pub local_only: bool,
+    /// 访问令牌明文（仅在内存中；写盘时替换为 token_ref）
     pub token: Option<String>,
-    pub git_provider: String, // github, gitlab, gitee
+    /// 访问令牌在安全存储中的引用
+    #[serde(default)]
+    pub token_ref: Option<String>,
+    pub git_provider: String, // github, gitlab, gitee, gitea, forgejo
+    /// 自建平台的网页根地址（如 https://git.example.com），为空时由远程地址推断
+    #[serde(default)]
+    pub provider_base_url: Option<String>,
+    /// 自建平台列表，用于根据远程地址识别平台
+    #[serde(default)]
+    pub custom_providers: Vec<CustomProvider>,
     pub enable_github_pages: bool,
+    /// 提交签名
+    #[serde(default)]
+    pub signing: SigningConfig,
+    /// 各操作的提交信息模板
+    #[serde(default)]
+    pub commit_templates: CommitTemplates,
+    /// 提交时执行仓库的 pre-commit / commit-msg 钩子
+    #[serde(default = "default_true")]
+    pub run_git_hooks: bool,
+    /// 发布站点时排除 "## 笔记" 区域
+    #[serde(default)]
+    pub pages_exclude_notes: bool,
+    /// 网络 git 命令（拉取、推送等）的超时秒数（0 表示不限制）
+    #[serde(default = "default_git_timeout_secs")]
+    pub git_timeout_secs: u32,
+    /// 本地 git 命令（gc、fsck 等）的超时秒数（0 表示不限制）
+    #[serde(default = "default_git_local_timeout_secs")]
+    pub git_local_timeout_secs: u32,
+    /// 空闲时自动维护仓库的间隔天数（0 表示不自动维护）
+    #[serde(default = "default_maintenance_interval_days")]
+    pub maintenance_interval_days: u32,
+    /// 回收站条目保留天数（0 表示永久保留）
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u32,
+    /// 额外的远程仓库（主仓库或只推送的备份镜像）
+    #[serde(default)]
+    pub remotes: Vec<RemoteConfig>,
+    /// SSH 私钥路径（为空时使用 ssh 默认密钥）
+    #[serde(default)]
+    pub ssh_key_path: Option<String>,
+    /// SSH 私钥口令明文（仅在内存中；写盘时替换为 ssh_passphrase_ref）
+    #[serde(default)]
+    pub ssh_passphrase: Option<String>,
+    /// SSH 私钥口令在安全存储中的引用
+    #[serde(default)]
+    pub ssh_passphrase_ref: Option<String>,
+    /// SSH known_hosts 文件路径（为空时使用 ~/.ssh/known_hosts）
+    #[serde(default)]
+    pub ssh_known_hosts_path: Option<String>,
+}
+
+impl Config {
+    /// 主远程仓库地址：优先 remote_url，否则取 remotes 中第一个 primary
+    pub fn primary_remote_url(&self) -> Option<String> {
+        self.remote_url
+            .clone()
+            .filter(|url| !url.is_empty())
+            .or_else(|| {
+                self.remotes
+                    .iter()
+                    .find(|r| r.role == "primary" && !r.url.is_empty())
+                    .map(|r| r.url.clone())
+            })
+    }
+
+    /// 是否启用同步：非仅本地模式且配置了主远程仓库
+    pub fn sync_enabled(&self) -> bool {
+        !self.local_only && self.primary_remote_url().is_some()
+    }
+
+    /// 只推送的备份镜像
+    pub fn mirror_remotes(&self) -> impl Iterator<Item = &RemoteConfig> {
+        self.remotes
+            .iter()
+            .filter(|r| r.role == "mirror" && !r.url.is_empty())
+    }
+}
+
+/// 远程仓库配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteConfig {
+    /// 远程名称（如 backup），主仓库固定使用 origin
+    pub name: String,
+    pub url: String,
+    /// 角色: primary（拉取/推送）, mirror（仅推送）
+    pub role: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_device_name() -> String {
+    crate::commit_message::hostname()
+}
+
+fn default_trash_retention_days() -> u32 {
+    30
+}
+
+fn default_git_timeout_secs() -> u32 {
+    120
+}
+
+fn default_git_local_timeout_secs() -> u32 {
+    600
+}
+
+fn default_maintenance_interval_days() -> u32 {
+    7
 }
 
 impl Default for Config {
@@ -19,10 +136,28 @@ impl Default for Config {
             local_path: String::new(),
             user_name: String::new(),
             user_email: String::new(),
+            device_name: default_device_name(),
             remote_url: None,
+            local_only: false,
             token: None,
+            token_ref: None,
             git_provider: "github".to_string(),
+            provider_base_url: None,
+            custom_providers: Vec::new(),
             enable_github_pages: false,
+            run_git_hooks: true,
+            signing: SigningConfig::default(),
+            commit_templates: CommitTemplates::default(),
+            pages_exclude_notes: false,
+            git_timeout_secs: default_git_timeout_secs(),
+            git_local_timeout_secs: default_git_local_timeout_secs(),
+            maintenance_interval_days: default_maintenance_interval_days(),
+            trash_retention_days: default_trash_retention_days(),
+            remotes: Vec::new(),
+            ssh_key_path: None,
+            ssh_passphrase: None,
+            ssh_passphrase_ref: None,
+            ssh_known_hosts_path: None,
         }
     }
 }
@@ -35,6 +170,35 @@ pub struct GitInfo {
     pub user_email: Option<String>,
     pub remote_url: Option<String>,
     pub git_provider: Option<String>,
+    /// 匹配到自建平台时的网页根地址
+    pub provider_base_url: Option<String>,
+}
+
+/// 自建 Git 平台（自建 GitLab、Gitea、Forgejo 等）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomProvider {
+    pub name: String,
+    /// 平台类型: github, gitlab, gitea, forgejo
+    pub kind: String,
+    /// 网页根地址，如 https://git.example.com:8443/gitlab
+    pub base_url: String,
+    /// 匹配远程地址的模式（* 为通配符），如 git@work:*、*git.example.com*
+    #[serde(default)]
+    pub url_patterns: Vec<String>,
+}
+
+/// 远程主机公钥（首次连接时由用户核对指纹）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SshHostKey {
+    pub host: String,
+    /// 公钥类型，如 ssh-ed25519
+    pub key_type: String,
+    /// SHA256 指纹，格式与 ssh-keygen -l 相同（SHA256:...）
+    pub fingerprint: String,
+    /// known_hosts 条目
+    pub entry: String,
 }
 
 /// 数据目录指针（存储在 Tauri 应用数据目录中）
@@ -110,3 +274,347 @@ pub struct PastUncompleted {
     /// 最后检查日期
     pub last_checked: String,
 }
+
+/// 修改过某个文件的提交记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCommit {
+    /// 完整提交 ID
+    pub id: String,
+    /// 短提交 ID
+    pub short_id: String,
+    pub author: String,
+    pub email: String,
+    /// 提交时间 (YYYY-MM-DD HH:MM:SS，本地时区)
+    pub time: String,
+    /// Unix 时间戳（秒）
+    pub timestamp: i64,
+    pub message: String,
+}
+
+/// 两个版本之间的任务级差异
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskDiff {
+    /// 新增的任务
+    pub added: Vec<String>,
+    /// 删除的任务
+    pub removed: Vec<String>,
+    /// 由未完成变为已完成的任务
+    pub completed: Vec<String>,
+    /// 由已完成变为未完成的任务
+    pub reopened: Vec<String>,
+}
+
+/// 回收站条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashEntry {
+    /// 唯一标识
+    pub id: String,
+    /// 类型: task, attachment
+    pub kind: String,
+    /// 所在文件（任务）或附件本身的相对路径
+    pub filepath: String,
+    /// 任务来源日期 (YYYY-MM-DD)
+    pub source_date: Option<String>,
+    /// 任务文本
+    pub text: Option<String>,
+    /// 任务所在区域
+    pub section: Option<String>,
+    /// 任务行及其子内容
+    #[serde(default)]
+    pub lines: Vec<String>,
+    /// 执行删除的提交 ID
+    pub commit: Option<String>,
+    /// 删除前的 HEAD（仍包含被删除的内容），即使删除提交失败也能据此恢复附件
+    #[serde(default)]
+    pub previous_commit: Option<String>,
+    /// 删除时间 (RFC 3339)
+    pub deleted_at: String,
+}
+
+/// 回收站数据
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashData {
+    pub entries: Vec<TrashEntry>,
+}
+
+/// 待推送同步日志（产生新提交或推送失败时记录，推送成功后清除）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncJournal {
+    /// 是否有待推送的提交
+    pub pending: bool,
+    /// 连续失败次数
+    pub attempts: u32,
+    /// 最近一次错误信息
+    pub last_error: Option<String>,
+    /// 最近一次尝试时间 (RFC 3339)
+    pub last_attempt: Option<String>,
+    /// 下次重试时间（Unix 时间戳，秒）
+    pub next_retry_at: Option<i64>,
+    /// 推送被远程拒绝（非快进、钩子拒绝等），重试无法解决，后台不再重试，等待手动同步
+    #[serde(default)]
+    pub blocked: bool,
+}
+
+/// 同步状态
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    /// 未推送的本地提交数
+    pub unpushed_commits: usize,
+    /// 待推送同步日志
+    pub journal: SyncJournal,
+}
+
+/// 单个远程仓库的推送状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteStatus {
+    pub name: String,
+    pub url: String,
+    /// 角色: primary, mirror
+    pub role: String,
+    /// 最近一次推送是否成功
+    pub ok: bool,
+    pub error: Option<String>,
+    /// 最近一次推送时间 (RFC 3339)
+    pub last_push: String,
+}
+
+/// 仓库健康检查发现的问题
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthIssue {
+    /// 问题类型: index_lock, detached_head, no_upstream, merge_in_progress,
+    /// operation_in_progress, desktop_data_tracked, gitignore_rule_missing, corrupted_objects
+    pub kind: String,
+    /// 严重程度: error, warning
+    pub severity: String,
+    pub message: String,
+    /// 是否可以由 repair_repo 自动修复
+    pub fixable: bool,
+}
+
+/// 仓库修复结果
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairReport {
+    /// 已执行的修复说明
+    pub fixed: Vec<String>,
+    /// 修复后仍然存在的问题
+    pub remaining: Vec<HealthIssue>,
+}
+
+/// 克隆进度（通过 clone-progress 事件发送给前端）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CloneProgress {
+    /// 阶段: receiving（接收对象）, resolving（解析增量）, checkout（检出文件）
+    pub stage: String,
+    pub received_objects: usize,
+    pub total_objects: usize,
+    pub received_bytes: usize,
+    pub indexed_deltas: usize,
+    pub total_deltas: usize,
+    pub checkout_files: usize,
+    pub total_checkout_files: usize,
+    /// 当前阶段完成百分比
+    pub percent: u32,
+}
+
+/// 导入现有文件夹时的文件重命名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdoptRename {
+    pub from: String,
+    pub to: String,
+}
+
+/// 现有 Markdown 文件夹的扫描结果（路径均相对于文件夹，使用 /）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderScan {
+    /// 已符合 YYYY/MM/MM-DD.md 结构的日文件
+    pub matched: Vec<String>,
+    /// 可以转换为日文件的近似文件（如 2026-10-17.md）
+    pub convertible: Vec<AdoptRename>,
+    /// 目标位置已有文件或多个文件对应同一天，无法自动转换
+    pub conflicts: Vec<AdoptRename>,
+    /// 其他 Markdown 文件（原样导入）
+    pub unmatched: Vec<String>,
+    /// 年/月/assets/ 下的附件（原样导入）
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    /// 文件夹是否已是 Git 仓库
+    pub is_git_repo: bool,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AdoptResult {
+    /// 导入提交中的文件数
+    pub imported_files: usize,
+    /// 其中的日文件数
+    pub day_files: usize,
+    /// 已转换的文件
+    pub renamed: Vec<AdoptRename>,
+    /// 导入提交（没有需要提交的文件时为空）
+    pub commit: Option<String>,
+}
+
+/// 提交签名设置
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningConfig {
+    /// 是否为所有提交签名
+    pub enabled: bool,
+    /// 签名格式: gpg（OpenPGP）, ssh
+    pub format: String,
+    /// gpg 为密钥 ID（为空时使用默认密钥），ssh 为私钥或公钥文件路径
+    pub key: Option<String>,
+    /// 签名程序（为空时 gpg 使用 gpg，ssh 使用 ssh-keygen）
+    pub program: Option<String>,
+}
+
+impl SigningConfig {
+    pub fn is_ssh(&self) -> bool {
+        self.format == "ssh"
+    }
+}
+
+/// 提交信息模板
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitTemplates {
+    /// 内置模板风格: zh（默认）, en, conventional
+    #[serde(default = "default_commit_style")]
+    pub style: String,
+    /// 按操作覆盖的模板（键为操作名，如 update、upload_attachment）
+    #[serde(default)]
+    pub overrides: HashMap<String, String>,
+}
+
+fn default_commit_style() -> String {
+    "zh".to_string()
+}
+
+impl Default for CommitTemplates {
+    fn default() -> Self {
+        Self {
+            style: default_commit_style(),
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+/// 仓库中的一个大文件对象
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobInfo {
+    pub oid: String,
+    /// 对象在历史中的路径（找不到时为空）
+    pub path: Option<String>,
+    pub size: u64,
+}
+
+/// 仓库体积统计
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoStats {
+    /// 对象总数（松散对象 + 包内对象）
+    pub object_count: u64,
+    pub loose_objects: u64,
+    /// 松散对象占用字节数
+    pub loose_bytes: u64,
+    pub pack_count: u64,
+    /// 包文件占用字节数
+    pub pack_bytes: u64,
+    /// 历史中最大的文件对象
+    pub largest_blobs: Vec<BlobInfo>,
+    /// 当前版本中各年份附件（YYYY/MM/assets/）的字节数
+    pub attachment_bytes_by_year: BTreeMap<String, u64>,
+    /// 最近一次维护
+    pub last_maintenance: Option<MaintenanceReport>,
+}
+
+/// 仓库维护（gc/repack/prune）结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceReport {
+    /// 执行时间 (RFC 3339)
+    pub ran_at: String,
+    /// 维护前后的对象库字节数（松散对象 + 包文件）
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub objects_before: u64,
+    pub objects_after: u64,
+}
+
+/// 拉取带来的某一天的变化
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayChange {
+    /// 日期 (YYYY-MM-DD)
+    pub date: String,
+    pub filepath: String,
+    /// 任务级差异
+    pub tasks: TaskDiff,
+}
+
+/// 一次拉取带来的远程更改摘要
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IncomingChanges {
+    /// 拉取前后的 HEAD（没有变化时为空）
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// 拉取到的提交数（不含合并提交）
+    pub commits: usize,
+    /// 这些提交的作者
+    pub authors: Vec<String>,
+    /// 这些提交记录的设备名称
+    pub devices: Vec<String>,
+    /// 有变化的日文件
+    pub days: Vec<DayChange>,
+}
+
+/// 推送结果：推送成功后发布站点失败不影响推送本身
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PushResult {
+    /// 发布 GitHub Pages 失败的原因（未启用或发布成功时为空）
+    pub pages_error: Option<String>,
+}
+
+/// 某台设备的提交活动
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceActivity {
+    /// 设备名称（提交中没有 Device trailer 时为空）
+    pub device: Option<String>,
+    pub commits: usize,
+    /// 有提交的天数
+    pub active_days: usize,
+    /// 使用过的提交作者
+    pub authors: Vec<String>,
+    /// 最早/最近提交时间 (RFC 3339)
+    pub first_commit: String,
+    pub last_commit: String,
+}
+
+/// 连接远程仓库的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachResult {
+    /// 与远程对齐后的分支
+    pub branch: String,
+    /// 处理方式: pushed（远程为空，直接推送）, up_to_date, fast_forward, merged, merged_unrelated（合并无关历史）
+    pub outcome: String,
+    /// 合并进来的远程更改
+    pub incoming: IncomingChanges,
+}
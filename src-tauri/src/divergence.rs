@@ -0,0 +1,290 @@
+use anyhow::Result;
+use chrono::Local;
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+
+/// 安全备份引用的命名空间（不会被推送，也不会被 gc 清除）
+pub const BACKUP_REF_PREFIX: &str = "refs/todo-backup/";
+
+/// 检查 reflog 中最近多少次远程跟踪分支的更新
+const REFLOG_DEPTH: usize = 50;
+
+/// 本地与远程历史的关系
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Divergence {
+    /// 类型: up_to_date, ahead, behind, diverged（可正常合并）,
+    /// rewritten（远程被强制推送，丢弃了本地已同步过的提交）, unrelated（没有共同历史，如远程仓库被重建）,
+    /// no_remote_branch
+    pub kind: String,
+    pub branch: String,
+    pub local: Option<String>,
+    pub remote: Option<String>,
+    /// 本地领先/落后远程的提交数（没有共同历史时为各自的提交数）
+    pub ahead: usize,
+    pub behind: usize,
+    /// 曾经同步到远程、但远程已不再包含的本地提交数
+    pub dropped: usize,
+}
+
+impl Divergence {
+    /// 是否需要用户选择处理方式（不能自动拉取合并）
+    pub fn needs_choice(&self) -> bool {
+        self.kind == "rewritten" || self.kind == "unrelated"
+    }
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind.as_str() {
+            "unrelated" => write!(f, "远程历史与本地没有共同提交（远程仓库可能已被重建），请选择处理方式"),
+            "rewritten" => write!(
+                f,
+                "远程历史已被改写（强制推送），{} 个已同步的本地提交不在远程中，请选择处理方式",
+                self.dropped
+            ),
+            _ => write!(f, "本地与远程历史不一致"),
+        }
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+/// 拉取失败：远程历史需要用户选择处理方式时附带 divergence，前端据此打开处理界面
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullError {
+    pub message: String,
+    pub divergence: Option<Divergence>,
+}
+
+impl From<anyhow::Error> for PullError {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            message: error.to_string(),
+            divergence: error.downcast_ref::<Divergence>().cloned(),
+        }
+    }
+}
+
+/// 比较本地 HEAD 与远程跟踪分支（调用前需已 fetch）
+pub fn classify(repo: &Repository, branch: &str) -> Result<Divergence> {
+    let tracking = format!("refs/remotes/origin/{}", branch);
+    let local = repo.head().ok().and_then(|h| h.target());
+    let remote = repo.refname_to_id(&tracking).ok();
+
+    let mut divergence = Divergence {
+        kind: "up_to_date".to_string(),
+        branch: branch.to_string(),
+        local: local.map(|id| id.to_string()),
+        remote: remote.map(|id| id.to_string()),
+        ahead: 0,
+        behind: 0,
+        dropped: 0,
+    };
+
+    let (local, remote) = match (local, remote) {
+        (_, None) => {
+            divergence.kind = "no_remote_branch".to_string();
+            return Ok(divergence);
+        }
+        (None, Some(_)) => {
+            divergence.kind = "behind".to_string();
+            return Ok(divergence);
+        }
+        (Some(local), Some(remote)) => (local, remote),
+    };
+
+    if repo.merge_base(local, remote).is_err() {
+        divergence.kind = "unrelated".to_string();
+        divergence.ahead = count_commits(repo, local, None)?;
+        divergence.behind = count_commits(repo, remote, None)?;
+        return Ok(divergence);
+    }
+
+    let (ahead, behind) = repo.graph_ahead_behind(local, remote)?;
+    divergence.ahead = ahead;
+    divergence.behind = behind;
+
+    // 远程跟踪分支曾指向的提交仍在本地历史中、但已不在远程历史中：远程被改写
+    if let Some(previous) = dropped_tip(repo, &tracking, local, remote)? {
+        divergence.kind = "rewritten".to_string();
+        divergence.dropped = count_commits(repo, previous, Some(remote))?;
+        return Ok(divergence);
+    }
+
+    divergence.kind = match (ahead, behind) {
+        (0, 0) => "up_to_date",
+        (_, 0) => "ahead",
+        (0, _) => "behind",
+        _ => "diverged",
+    }
+    .to_string();
+    Ok(divergence)
+}
+
+/// 在远程跟踪分支的 reflog 中查找被远程丢弃、但本地仍包含的旧提交
+fn dropped_tip(repo: &Repository, tracking: &str, local: Oid, remote: Oid) -> Result<Option<Oid>> {
+    let Ok(reflog) = repo.reflog(tracking) else {
+        return Ok(None);
+    };
+
+    for entry in reflog.iter().take(REFLOG_DEPTH) {
+        for previous in [entry.id_old(), entry.id_new()] {
+            if previous.is_zero() || previous == remote || repo.find_commit(previous).is_err() {
+                continue;
+            }
+            let in_local = previous == local || repo.graph_descendant_of(local, previous)?;
+            let in_remote = repo.graph_descendant_of(remote, previous)?;
+            if in_local && !in_remote {
+                return Ok(Some(previous));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// from 可达、hide 不可达的提交数
+fn count_commits(repo: &Repository, from: Oid, hide: Option<Oid>) -> Result<usize> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(from)?;
+    if let Some(hide) = hide {
+        revwalk.hide(hide)?;
+    }
+    Ok(revwalk.count())
+}
+
+/// 在任何破坏性操作前为本地和远程分支创建备份引用，返回引用名
+pub fn create_backup_refs(repo: &Repository, divergence: &Divergence) -> Result<Vec<String>> {
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    let mut refs = vec![];
+
+    for (side, oid) in [("local", &divergence.local), ("remote", &divergence.remote)] {
+        let Some(oid) = oid else { continue };
+        let name = format!("{}{}/{}-{}", BACKUP_REF_PREFIX, divergence.branch, stamp, side);
+        repo.reference(&name, Oid::from_str(oid)?, true, "sync safety backup")?;
+        refs.push(name);
+    }
+
+    Ok(refs)
+}
+
+/// 处理历史分歧的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DivergenceResolution {
+    /// 选择: force_push（保留本地并强制推送）, take_remote（采用远程并备份本地）, merge_unrelated（合并两份历史）
+    pub choice: String,
+    /// 操作前创建的备份引用
+    pub backup_refs: Vec<String>,
+    /// 本地因此发生的变化
+    pub incoming: crate::config::IncomingChanges,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+
+    const TRACKING: &str = "refs/remotes/origin/main";
+
+    fn init_repo(name: &str) -> (std::path::PathBuf, Repository) {
+        let dir = std::env::temp_dir().join(format!("todo-divergence-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        (dir, repo)
+    }
+
+    fn commit(repo: &Repository, message: &str, parents: &[Oid]) -> Oid {
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+        let parents: Vec<_> = parents.iter().map(|id| repo.find_commit(*id).unwrap()).collect();
+        let parents: Vec<_> = parents.iter().collect();
+        repo.commit(None, &signature, &signature, message, &tree, &parents).unwrap()
+    }
+
+    fn set_local(repo: &Repository, oid: Oid) {
+        repo.reference("refs/heads/main", oid, true, "test").unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+    }
+
+    /// 模拟 fetch 更新远程跟踪分支（会记录 reflog）
+    fn set_remote(repo: &Repository, oid: Oid) {
+        repo.reference(TRACKING, oid, true, "fetch").unwrap();
+    }
+
+    #[test]
+    fn classifies_diverged_history() {
+        let (dir, repo) = init_repo("diverged");
+        let base = commit(&repo, "base", &[]);
+        set_local(&repo, base);
+        assert_eq!(classify(&repo, "main").unwrap().kind, "no_remote_branch");
+
+        set_remote(&repo, base);
+        assert_eq!(classify(&repo, "main").unwrap().kind, "up_to_date");
+
+        let local = commit(&repo, "local", &[base]);
+        set_local(&repo, local);
+        assert_eq!(classify(&repo, "main").unwrap().kind, "ahead");
+
+        let remote = commit(&repo, "remote", &[base]);
+        set_remote(&repo, remote);
+        let divergence = classify(&repo, "main").unwrap();
+        assert_eq!(divergence.kind, "diverged");
+        assert_eq!((divergence.ahead, divergence.behind, divergence.dropped), (1, 1, 0));
+        assert!(!divergence.needs_choice());
+
+        set_local(&repo, base);
+        assert_eq!(classify(&repo, "main").unwrap().kind, "behind");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn classifies_unrelated_history() {
+        let (dir, repo) = init_repo("unrelated");
+        let base = commit(&repo, "base", &[]);
+        let local = commit(&repo, "local", &[base]);
+        set_local(&repo, local);
+
+        // 远程仓库被重建：新的根提交
+        let remote = commit(&repo, "recreated", &[]);
+        set_remote(&repo, remote);
+
+        let divergence = classify(&repo, "main").unwrap();
+        assert_eq!(divergence.kind, "unrelated");
+        assert_eq!((divergence.ahead, divergence.behind), (2, 1));
+        assert!(divergence.needs_choice());
+        assert_eq!(divergence.remote, Some(remote.to_string()));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn classifies_rewritten_history() {
+        let (dir, repo) = init_repo("rewritten");
+        let base = commit(&repo, "base", &[]);
+        let synced = commit(&repo, "synced", &[base]);
+        set_remote(&repo, synced);
+
+        // 本地在已同步的提交上继续工作，远程被强制推送为另一条历史
+        let local = commit(&repo, "local", &[synced]);
+        set_local(&repo, local);
+        let forced = commit(&repo, "forced", &[base]);
+        set_remote(&repo, forced);
+
+        let divergence = classify(&repo, "main").unwrap();
+        assert_eq!(divergence.kind, "rewritten");
+        assert_eq!(divergence.dropped, 1);
+        assert_eq!((divergence.ahead, divergence.behind), (2, 1));
+        assert!(divergence.needs_choice());
+
+        // 错误信息以结构化形式传给前端
+        let error = PullError::from(anyhow::Error::from(divergence));
+        assert_eq!(error.divergence.unwrap().kind, "rewritten");
+        assert!(error.message.contains("请选择处理方式"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,7 +1,23 @@
-use git2::{Repository, Signature};
+use git2::{Repository, Signature, Sort};
 use anyhow::{Result, anyhow};
 use std::path::Path;
-use crate::config::{Config, GitInfo};
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use chrono::{Local, TimeZone};
+use crate::config::{Config, CustomProvider, GitInfo, FileCommit, TaskDiff, RemoteStatus, HealthIssue, RepairReport, CloneProgress, RepoStats, BlobInfo, MaintenanceReport, IncomingChanges, DayChange, DeviceActivity, AttachResult};
+use crate::task_parser;
+use crate::ssh::{self, SshSettings};
+use crate::credentials;
+use crate::provider;
+use crate::pages;
+use crate::hooks;
+use crate::signing;
+use crate::adopt;
+use crate::divergence::{self, Divergence, DivergenceResolution};
+use crate::commit_message::{self, CommitAction, MessageVars};
+use crate::sync_queue;
+use crate::process;
 
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
@@ -9,6 +25,15 @@ use std::os::windows::process::CommandExt;
 #[cfg(windows)]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
+/// 仅本地模式下调用同步时的错误信息
+pub const LOCAL_ONLY_ERROR: &str = "仅本地模式，未启用同步";
+
+/// 仓库统计中列出的最大文件对象数
+const LARGEST_BLOBS: usize = 10;
+
+/// 在 .git/config 中标记备份镜像的键（remote.<name>.todoRole = mirror）
+const REMOTE_ROLE_KEY: &str = "todoRole";
+
 pub struct GitManager {
     repo: Repository,
     config: Config,
@@ -29,20 +54,32 @@ impl GitManager {
     }
 
     /// 克隆远程仓库到本地，返回实际克隆的路径
-    /// 使用系统 git 命令以支持 Git Credential Manager
-    pub fn clone_repo(url: &str, base_path: &str, token: Option<&str>) -> Result<String> {
-        // 从 URL 提取仓库名
+    /// depth 为 Some 时只获取最近的若干个提交；cancel 被置位时中止并删除未完成的目录
+    #[allow(clippy::too_many_arguments)]
+    pub fn clone_repo(
+        url: &str,
+        base_path: &str,
+        token: Option<&str>,
+        git_provider: Option<&str>,
+        ssh_settings: &SshSettings,
+        depth: Option<u32>,
+        cancel: &AtomicBool,
+        on_progress: &mut dyn FnMut(&CloneProgress),
+    ) -> Result<String> {
+        // 从 URL 提取仓库名（兼容 git@host:user/repo.git）
         let repo_name = url
             .trim_end_matches('/')
             .trim_end_matches(".git")
-            .rsplit('/')
+            .rsplit(['/', ':'])
             .next()
+            .filter(|name| !name.is_empty())
             .ok_or_else(|| anyhow!("无法从 URL 解析仓库名"))?;
 
         let clone_path = Path::new(base_path).join(repo_name);
 
         // 如果目标目录已存在且不为空，报错
-        if clone_path.exists() {
+        let existed = clone_path.exists();
+        if existed {
             let is_empty = clone_path.read_dir()?.next().is_none();
             if !is_empty {
                 return Err(anyhow!("目录 {} 已存在且不为空", repo_name));
@@ -52,40 +89,125 @@ impl GitManager {
         // 确保父目录存在
         std::fs::create_dir_all(base_path)?;
 
-        // 构建克隆 URL（如果有 token，使用 x-access-token 格式）
-        let clone_url = match token {
-            Some(t) if !t.is_empty() => {
-                if let Some(pos) = url.find("://") {
-                    format!("{}://x-access-token:{}@{}", &url[..pos], t, &url[pos + 3..])
-                } else {
-                    url.to_string()
-                }
+        // HTTPS 地址的 token 只在认证回调中使用，不写入 URL（否则会保存在 .git/config 中）
+        let clone_url = credentials::strip_credentials(url).unwrap_or_else(|| url.to_string());
+        let https_credentials = match token {
+            Some(t) if !t.is_empty() && !ssh::is_ssh_url(url) => {
+                let provider_id = match git_provider {
+                    Some(id) => id.to_string(),
+                    None => provider::detect_provider(url, &[]).id,
+                };
+                Some(provider::provider_for(&provider_id, None).credentials(t))
             }
-            _ => url.to_string(),
+            _ => None,
         };
 
-        // 执行克隆
-        let mut cmd = std::process::Command::new("git");
-        cmd.args(["clone", &clone_url, clone_path.to_str().unwrap()]);
+        cancel.store(false, Ordering::SeqCst);
+
+        let result = Self::clone_with_progress(
+            &clone_url,
+            &clone_path,
+            https_credentials,
+            ssh_settings,
+            depth,
+            cancel,
+            on_progress,
+        );
+
+        // 检出阶段无法中断，完成后再检查是否已取消
+        let cancelled = cancel.load(Ordering::SeqCst);
+        if result.is_err() || cancelled {
+            // 清理未完成的目录；原本存在的空目录保留
+            let _ = std::fs::remove_dir_all(&clone_path);
+            if existed {
+                let _ = std::fs::create_dir_all(&clone_path);
+            }
+        }
+        if cancelled {
+            return Err(anyhow!("克隆已取消"));
+        }
+        if let Err(e) = result {
+            return Err(anyhow!("{}", credentials::redact_secret(e.message(), token.unwrap_or(""))));
+        }
+
+        // 返回实际克隆路径
+        Ok(clone_path.to_string_lossy().to_string())
+    }
 
-        #[cfg(windows)]
-        cmd.creation_flags(CREATE_NO_WINDOW);
+    fn clone_with_progress(
+        url: &str,
+        clone_path: &Path,
+        https_credentials: Option<(String, String)>,
+        ssh_settings: &SshSettings,
+        depth: Option<u32>,
+        cancel: &AtomicBool,
+        on_progress: &mut dyn FnMut(&CloneProgress),
+    ) -> std::result::Result<(), git2::Error> {
+        let progress = RefCell::new(CloneProgress::default());
+        let last_emit = Cell::new(Instant::now() - PROGRESS_INTERVAL);
+        let on_progress = RefCell::new(on_progress);
+
+        // 限制事件频率，阶段变化和完成时立即发送
+        let emit = |update: &dyn Fn(&mut CloneProgress)| {
+            let mut current = progress.borrow_mut();
+            let previous_stage = current.stage.clone();
+            let previous_percent = current.percent;
+            update(&mut current);
+
+            let finished = current.percent == 100 && previous_percent != 100;
+            if current.stage != previous_stage || finished || last_emit.get().elapsed() >= PROGRESS_INTERVAL {
+                last_emit.set(Instant::now());
+                (on_progress.borrow_mut())(&current);
+            }
+        };
 
-        let output = cmd
-            .output()
-            .map_err(|e| anyhow!("无法执行 git 命令: {}", e))?;
+        let mut callbacks = remote_callbacks(https_credentials, ssh_settings);
+        callbacks.transfer_progress(|stats| {
+            emit(&|p: &mut CloneProgress| {
+                p.received_objects = stats.received_objects();
+                p.total_objects = stats.total_objects();
+                p.received_bytes = stats.received_bytes();
+                p.indexed_deltas = stats.indexed_deltas();
+                p.total_deltas = stats.total_deltas();
+                if stats.total_deltas() > 0 && stats.received_objects() == stats.total_objects() {
+                    p.stage = "resolving".to_string();
+                    p.percent = percent(stats.indexed_deltas(), stats.total_deltas());
+                } else {
+                    p.stage = "receiving".to_string();
+                    p.percent = percent(stats.received_objects(), stats.total_objects());
+                }
+            });
+            // 返回 false 时 libgit2 中止传输
+            !cancel.load(Ordering::SeqCst)
+        });
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("{}", stderr.trim()));
+        let mut fetch_options = git2::FetchOptions::new();
+        fetch_options.remote_callbacks(callbacks);
+        if let Some(depth) = depth.filter(|d| *d > 0) {
+            fetch_options.depth(depth as i32);
         }
 
-        // 返回实际克隆路径
-        Ok(clone_path.to_string_lossy().to_string())
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.progress(|_path, current, total| {
+            emit(&|p: &mut CloneProgress| {
+                p.stage = "checkout".to_string();
+                p.checkout_files = current;
+                p.total_checkout_files = total;
+                p.percent = percent(current, total);
+            });
+        });
+
+        git2::build::RepoBuilder::new()
+            .fetch_options(fetch_options)
+            .with_checkout(checkout)
+            .clone(url, clone_path)?;
+
+        Ok(())
     }
 
     /// 检测现有 Git 仓库的配置信息
-    pub fn detect_config(path: &str) -> Result<Option<GitInfo>> {
+    /// custom_providers 为用户配置的自建平台，用于识别平台类型
+    pub fn detect_config(path: &str, custom_providers: &[CustomProvider]) -> Result<Option<GitInfo>> {
         let path = Path::new(path);
 
         if !path.join(".git").exists() {
@@ -98,32 +220,36 @@ impl GitManager {
         let user_name = config.get_string("user.name").ok();
         let user_email = config.get_string("user.email").ok();
 
-        // 获取远程仓库 URL
+        // 获取远程仓库 URL（不返回其中的凭据）
         let remote_url = repo.find_remote("origin")
             .ok()
-            .and_then(|remote| remote.url().map(|s| s.to_string()));
+            .and_then(|remote| remote.url().map(|s| s.to_string()))
+            .map(|url| credentials::strip_credentials(&url).unwrap_or(url));
 
         // 根据 URL 判断 Git 提供商
-        let git_provider = remote_url.as_ref().map(|url| {
-            if url.contains("github.com") {
-                "github".to_string()
-            } else if url.contains("gitlab.com") {
-                "gitlab".to_string()
-            } else if url.contains("gitee.com") {
-                "gitee".to_string()
-            } else {
-                "github".to_string()
-            }
-        });
+        let detected = remote_url
+            .as_ref()
+            .map(|url| provider::detect_provider(url, custom_providers));
 
         Ok(Some(GitInfo {
             user_name,
             user_email,
             remote_url,
-            git_provider,
+            git_provider: detected.as_ref().map(|d| d.id.clone()),
+            provider_base_url: detected.and_then(|d| d.base_url),
         }))
     }
 
+    /// 本地仓库目录
+    pub fn local_path(&self) -> &str {
+        &self.config.local_path
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
     /// 检查目录是否是有效的 Git 仓库
     pub fn is_git_repo(path: &str) -> bool {
         Path::new(path).join(".git").exists()
@@ -146,27 +272,166 @@ impl GitManager {
             let _ = self.untrack_desktop_data();
         }
 
-        // 设置远程仓库
-        if let Some(remote_url) = &self.config.remote_url {
-            if !remote_url.is_empty() {
-                match self.repo.find_remote("origin") {
-                    Ok(_) => {
-                        // 更新已存在的远程仓库
-                        self.repo.remote_set_url("origin", remote_url)?;
-                    }
-                    Err(_) => {
-                        // 添加新的远程仓库
-                        self.repo.remote("origin", remote_url)?;
-                    }
-                }
+        // 清除旧版本写入远程 URL 中的令牌
+        self.scrub_remote_credentials()?;
+
+        // 设置远程仓库（凭据通过 askpass 提供，不写入 URL）
+        if let Some(remote_url) = self.config.primary_remote_url() {
+            let remote_url = credentials::strip_credentials(&remote_url).unwrap_or(remote_url);
+            self.set_remote("origin", &remote_url)?;
+        }
+
+        // 设置备份镜像
+        self.configure_mirrors()?;
+
+        // 提交签名（系统 git 的合并提交也使用相同设置）
+        signing::configure_repo(&self.repo, &self.config.signing)?;
+
+        Ok(())
+    }
+
+    /// 移除所有远程 URL（含 pushurl）中的用户名/令牌，返回是否有修改
+    fn scrub_remote_credentials(&self) -> Result<bool> {
+        let mut scrubbed = false;
+
+        for name in self.repo.remotes()?.iter().flatten() {
+            let remote = self.repo.find_remote(name)?;
+
+            if let Some(clean) = remote.url().and_then(credentials::strip_credentials) {
+                self.repo.remote_set_url(name, &clean)?;
+                scrubbed = true;
+            }
+            if let Some(clean) = remote.pushurl().and_then(credentials::strip_credentials) {
+                self.repo.remote_set_pushurl(name, Some(&clean))?;
+                scrubbed = true;
             }
         }
 
+        Ok(scrubbed)
+    }
+
+    /// 添加或更新远程仓库
+    fn set_remote(&self, name: &str, url: &str) -> Result<()> {
+        match self.repo.find_remote(name) {
+            Ok(_) => {
+                // 更新已存在的远程仓库
+                self.repo.remote_set_url(name, url)?;
+            }
+            Err(_) => {
+                // 添加新的远程仓库
+                self.repo.remote(name, url)?;
+            }
+        }
         Ok(())
     }
 
+    /// 按配置同步备份镜像：添加/更新配置中的镜像，移除已不在配置中的镜像
+    fn configure_mirrors(&self) -> Result<()> {
+        let mut git_config = self.repo.config()?;
+        let mut wanted: Vec<&str> = Vec::new();
+
+        for mirror in self.config.mirror_remotes() {
+            if mirror.name.is_empty() || mirror.name == "origin" {
+                return Err(anyhow!("备份镜像名称无效: {:?}", mirror.name));
+            }
+            self.set_remote(&mirror.name, &mirror.url)?;
+            git_config.set_str(&format!("remote.{}.{}", mirror.name, REMOTE_ROLE_KEY), "mirror")?;
+            wanted.push(&mirror.name);
+        }
+
+        for (name, _) in Self::mirror_remotes_at(&self.config.local_path)? {
+            if !wanted.contains(&name.as_str()) {
+                self.repo.remote_delete(&name)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 读取仓库中标记为备份镜像的远程（名称, URL）
+    pub fn mirror_remotes_at(local_path: &str) -> Result<Vec<(String, String)>> {
+        let repo = Repository::open(local_path)?;
+        let git_config = repo.config()?;
+        let mut mirrors = Vec::new();
+
+        for name in repo.remotes()?.iter().flatten() {
+            let role = git_config
+                .get_string(&format!("remote.{}.{}", name, REMOTE_ROLE_KEY))
+                .unwrap_or_default();
+            if role != "mirror" {
+                continue;
+            }
+            if let Some(url) = repo.find_remote(name)?.url() {
+                mirrors.push((name.to_string(), url.to_string()));
+            }
+        }
+
+        Ok(mirrors)
+    }
+
+    /// 推送所有分支到每个备份镜像，返回各镜像的推送状态
+    pub fn push_mirrors_at(config: &Config) -> Vec<RemoteStatus> {
+        let mirrors = Self::mirror_remotes_at(&config.local_path).unwrap_or_default();
+
+        mirrors
+            .into_iter()
+            .map(|(name, url)| {
+                let result = git_env(config)
+                    .and_then(|env| run_git(&config.local_path, &["push", &name, "--all"], &env, network_timeout(config)));
+                RemoteStatus {
+                    name,
+                    url,
+                    role: "mirror".to_string(),
+                    ok: result.is_ok(),
+                    error: result.err().map(|e| e.to_string()),
+                    last_push: Local::now().to_rfc3339(),
+                }
+            })
+            .collect()
+    }
+
+    /// 主仓库 origin 的推送状态
+    pub fn primary_status(local_path: &str, result: &Result<()>) -> RemoteStatus {
+        let url = Self::origin_url_at(local_path).unwrap_or_default();
+
+        RemoteStatus {
+            name: "origin".to_string(),
+            url,
+            role: "primary".to_string(),
+            ok: result.is_ok(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            last_push: Local::now().to_rfc3339(),
+        }
+    }
+
     /// 确保 .gitignore 文件包含 .desktop_data/ 目录
     fn ensure_gitignore(&self) -> Result<()> {
+        // 如果仓库不为空，提交 .gitignore 更新
+        if self.write_gitignore_rule()? && !self.repo.is_empty()? {
+            let mut index = self.repo.index()?;
+            index.add_path(Path::new(".gitignore"))?;
+            index.write()?;
+
+            let tree_id = index.write_tree()?;
+            let tree = self.repo.find_tree(tree_id)?;
+            let signature = Signature::now(&self.config.user_name, &self.config.user_email)?;
+            let parent_commit = self.repo.head()?.peel_to_commit()?;
+
+            self.commit_to(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                &self.commit_message(CommitAction::Gitignore, &MessageVars::default()),
+                &tree,
+                &[&parent_commit],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// 在 .gitignore 中写入 .desktop_data/ 规则（不提交），返回是否有修改
+    fn write_gitignore_rule(&self) -> Result<bool> {
         let gitignore_path = Path::new(&self.config.local_path).join(".gitignore");
         let desktop_data_rule = ".desktop_data/";
 
@@ -176,13 +441,7 @@ impl GitManager {
             String::new()
         };
 
-        // 检查是否已包含 .desktop_data/ 规则
-        let has_rule = content.lines().any(|line| {
-            let trimmed = line.trim();
-            trimmed == desktop_data_rule || trimmed == ".desktop_data"
-        });
-
-        if !has_rule {
+        if !has_desktop_data_rule(&content) {
             // 添加规则到 .gitignore
             let new_content = if content.is_empty() {
                 format!("# 本地客户端数据（不同步）\n{}\n", desktop_data_rule)
@@ -193,30 +452,10 @@ impl GitManager {
             };
 
             std::fs::write(&gitignore_path, &new_content)?;
-
-            // 如果仓库不为空，提交 .gitignore 更新
-            if !self.repo.is_empty()? {
-                let mut index = self.repo.index()?;
-                index.add_path(Path::new(".gitignore"))?;
-                index.write()?;
-
-                let tree_id = index.write_tree()?;
-                let tree = self.repo.find_tree(tree_id)?;
-                let signature = Signature::now(&self.config.user_name, &self.config.user_email)?;
-                let parent_commit = self.repo.head()?.peel_to_commit()?;
-
-                self.repo.commit(
-                    Some("HEAD"),
-                    &signature,
-                    &signature,
-                    "添加 .desktop_data 到 .gitignore",
-                    &tree,
-                    &[&parent_commit],
-                )?;
-            }
+            return Ok(true);
         }
 
-        Ok(())
+        Ok(false)
     }
 
     /// 从 git 跟踪中移除 .desktop_data 目录（但保留本地文件）
@@ -248,11 +487,11 @@ impl GitManager {
         let signature = Signature::now(&self.config.user_name, &self.config.user_email)?;
         let parent_commit = self.repo.head()?.peel_to_commit()?;
 
-        self.repo.commit(
+        self.commit_to(
             Some("HEAD"),
             &signature,
             &signature,
-            "停止跟踪 .desktop_data（本地客户端数据不再同步）",
+            &self.commit_message(CommitAction::UntrackDesktopData, &MessageVars::default()),
             &tree,
             &[&parent_commit],
         )?;
@@ -260,7 +499,18 @@ impl GitManager {
         Ok(true)
     }
 
+    /// 空仓库使用 init.defaultBranch（未配置时为 main），而不是 libgit2 默认的 master
+    fn set_default_branch(&self) -> Result<()> {
+        let default_branch = git2::Config::open_default()
+            .and_then(|c| c.get_string("init.defaultBranch"))
+            .unwrap_or_else(|_| "main".to_string());
+        self.repo.set_head(&format!("refs/heads/{}", default_branch))?;
+        Ok(())
+    }
+
     fn create_initial_commit(&self) -> Result<()> {
+        self.set_default_branch()?;
+
         // 创建 README.md
         let readme_path = Path::new(&self.config.local_path).join("README.md");
         std::fs::write(
@@ -278,11 +528,11 @@ impl GitManager {
 
         let signature = Signature::now(&self.config.user_name, &self.config.user_email)?;
 
-        self.repo.commit(
+        self.commit_to(
             Some("HEAD"),
             &signature,
             &signature,
-            "初始化仓库",
+            &self.commit_message(CommitAction::Init, &MessageVars::default()),
             &tree,
             &[],
         )?;
@@ -290,28 +540,53 @@ impl GitManager {
         Ok(())
     }
 
-    pub fn add_and_commit(&self, filepath: &str, message: &str) -> Result<()> {
+    /// 将扫描到的文件（paths，未被 .gitignore 忽略的）作为一次导入提交（含 .gitignore 规则），
+    /// removed 为已移动到新位置的原路径；返回提交 id 和提交中变化的文件数，没有变化时返回 None
+    pub fn import_all(&self, paths: &[String], removed: &[String], message: &str) -> Result<Option<(String, usize)>> {
+        let mut git_config = self.repo.config()?;
+        git_config.set_str("user.name", &self.config.user_name)?;
+        git_config.set_str("user.email", &self.config.user_email)?;
+
+        self.write_gitignore_rule()?;
+
+        // 只暂存这些文件，目录中的其他文件（密钥、临时文件等）保持原样
         let mut index = self.repo.index()?;
-        index.add_path(Path::new(filepath))?;
+        let staged = paths.iter().map(|p| p.as_str()).chain([".gitignore"]);
+        index.add_all(staged, git2::IndexAddOption::DISABLE_PATHSPEC_MATCH, None)?;
+        for path in removed {
+            if index.get_path(Path::new(path), 0).is_some() {
+                index.remove_path(Path::new(path))?;
+            }
+        }
         index.write()?;
 
         let tree_id = index.write_tree()?;
         let tree = self.repo.find_tree(tree_id)?;
 
-        let signature = Signature::now(&self.config.user_name, &self.config.user_email)?;
+        let parent = if self.repo.is_empty()? {
+            self.set_default_branch()?;
+            None
+        } else {
+            Some(self.repo.head()?.peel_to_commit()?)
+        };
 
-        let parent_commit = self.repo.head()?.peel_to_commit()?;
+        let parent_tree = parent.as_ref().map(|p| p.tree()).transpose()?;
+        let diff = self.repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+        let changed = diff.deltas().len();
+        if changed == 0 {
+            return Ok(None);
+        }
 
-        self.repo.commit(
-            Some("HEAD"),
-            &signature,
-            &signature,
-            message,
-            &tree,
-            &[&parent_commit],
-        )?;
+        let signature = Signature::now(&self.config.user_name, &self.config.user_email)?;
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        let commit = self.commit_to(Some("HEAD"), &signature, &signature, message, &tree, &parents)?;
 
-        Ok(())
+        Ok(Some((commit.to_string(), changed)))
+    }
+
+    pub fn add_and_commit(&self, filepath: &str, message: &str) -> Result<()> {
+        self.stage_path(filepath)?;
+        self.commit_staged(message)
     }
 
     /// 从 Git 中删除文件并提交
@@ -320,6 +595,79 @@ impl GitManager {
         index.remove_path(Path::new(filepath))?;
         index.write()?;
 
+        self.commit_staged(message)
+    }
+
+    /// 暂存文件：libgit2 会按 .gitattributes 处理换行符（text/eol），
+    /// 但不支持外部过滤器（filter=lfs 等），这类文件交给系统 git 暂存
+    fn stage_path(&self, filepath: &str) -> Result<()> {
+        let filter = self
+            .repo
+            .get_attr(Path::new(filepath), "filter", git2::AttrCheckFlags::default())?
+            .map(|value| git2::AttrValue::from_string(Some(value)));
+
+        if let Some(git2::AttrValue::String(_)) = filter {
+            run_git(&self.config.local_path, &["add", "--", filepath], &[], local_timeout(&self.config))?;
+            return Ok(());
+        }
+
+        let mut index = self.repo.index()?;
+        index.add_path(Path::new(filepath))?;
+        index.write()?;
+        Ok(())
+    }
+
+    /// 创建提交（添加设备 trailer，按配置签名），参数与 Repository::commit 相同
+    fn commit_to(
+        &self,
+        update_ref: Option<&str>,
+        author: &Signature,
+        committer: &Signature,
+        message: &str,
+        tree: &git2::Tree,
+        parents: &[&git2::Commit],
+    ) -> Result<git2::Oid> {
+        let message = commit_message::with_device_trailer(&self.config, message);
+        self.commit_raw(update_ref, author, committer, &message, tree, parents)
+    }
+
+    /// 按原样使用提交信息创建提交（按配置签名）
+    fn commit_raw(
+        &self,
+        update_ref: Option<&str>,
+        author: &Signature,
+        committer: &Signature,
+        message: &str,
+        tree: &git2::Tree,
+        parents: &[&git2::Commit],
+    ) -> Result<git2::Oid> {
+        let oid = signing::commit(&self.repo, &self.config.signing, local_timeout(&self.config), update_ref, author, committer, message, tree, parents)?;
+
+        // 记录到同步日志，离线期间的提交由后台线程在恢复连接后推送
+        if update_ref == Some("HEAD") && self.config.sync_enabled() {
+            sync_queue::mark_pending(&self.config.local_path);
+        }
+        Ok(oid)
+    }
+
+    /// 提交已暂存的更改，按配置在前后执行 pre-commit、commit-msg 和 post-commit 钩子
+    fn commit_staged(&self, message: &str) -> Result<()> {
+        let run_hooks = self.config.run_git_hooks;
+
+        // pre-commit 可能修改并重新暂存文件，执行后重新读取索引
+        if run_hooks {
+            hooks::run_hook(&self.repo, "pre-commit", &[], local_timeout(&self.config))?;
+        }
+        // 先添加设备 trailer，commit-msg 钩子检查的就是最终提交的信息
+        let message = commit_message::with_device_trailer(&self.config, message);
+        let message = if run_hooks {
+            hooks::run_commit_msg_hook(&self.repo, &message, local_timeout(&self.config))?
+        } else {
+            message
+        };
+
+        let mut index = self.repo.index()?;
+        index.read(true)?;
         let tree_id = index.write_tree()?;
         let tree = self.repo.find_tree(tree_id)?;
 
@@ -327,60 +675,641 @@ impl GitManager {
 
         let parent_commit = self.repo.head()?.peel_to_commit()?;
 
-        self.repo.commit(
+        self.commit_raw(
             Some("HEAD"),
             &signature,
             &signature,
-            message,
+            &message,
             &tree,
             &[&parent_commit],
         )?;
 
+        if run_hooks {
+            // post-commit 的结果不影响提交
+            if let Err(e) = hooks::run_hook(&self.repo, "post-commit", &[], local_timeout(&self.config)) {
+                eprintln!("{}", e);
+            }
+        }
+
         Ok(())
     }
 
     pub fn push(&self) -> Result<()> {
-        // 使用系统 git 命令以支持 Git Credential Manager
-        let mut cmd = std::process::Command::new("git");
-        cmd.args(["push"])
-            .current_dir(&self.config.local_path);
+        Self::push_at(&self.config)
+    }
+
+    pub fn pull(&self) -> Result<IncomingChanges> {
+        Self::pull_at(&self.config)
+    }
+
+    /// 按配置执行 git push（不需要持有 GitManager，供后台线程使用）
+    pub fn push_at(config: &Config) -> Result<()> {
+        if !config.sync_enabled() {
+            return Err(anyhow!(LOCAL_ONLY_ERROR));
+        }
+        let env = git_env(config)?;
+
+        // 首次推送：对齐分支名并设置上游
+        match Self::prepare_upstream(config, &env)? {
+            Some(branch) => run_git(&config.local_path, &["push", "-u", "origin", &branch], &env, network_timeout(config)).map(|_| ()),
+            None => run_git(&config.local_path, &["push"], &env, network_timeout(config)).map(|_| ()),
+        }
+    }
 
-        #[cfg(windows)]
-        cmd.creation_flags(CREATE_NO_WINDOW);
+    /// 按配置执行 git pull（不需要持有 GitManager，供后台线程使用），返回拉取到的远程更改摘要
+    pub fn pull_at(config: &Config) -> Result<IncomingChanges> {
+        if !config.sync_enabled() {
+            return Err(anyhow!(LOCAL_ONLY_ERROR));
+        }
+        let env = git_env(config)?;
+        let before = head_oid(&config.local_path);
+
+        Self::pull_branch(config, &env)?;
+
+        match head_oid(&config.local_path) {
+            Some(after) if before != Some(after) => Self::incoming_changes(&config.local_path, before, after),
+            _ => Ok(IncomingChanges::default()),
+        }
+    }
 
-        let output = cmd
-            .output()
-            .map_err(|e| anyhow!("无法执行 git 命令: {}", e))?;
+    /// 拉取当前分支（没有上游时与远程同名分支对齐并设置上游）
+    /// 远程历史被改写或与本地无关时不合并，返回 Divergence 错误由用户选择处理方式
+    fn pull_branch(config: &Config, env: &[(String, String)]) -> Result<()> {
+        let (branch, set_upstream) = match Self::prepare_upstream(config, env)? {
+            Some(branch) => (branch, true),
+            None => match Self::upstream_branch(&config.local_path)? {
+                Some(branch) => (branch, false),
+                None => return run_git(&config.local_path, &["pull"], env, network_timeout(config)).map(|_| ()),
+            },
+        };
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("{}", stderr.trim()));
+        // 远程还没有该分支：等待首次推送
+        let divergence = Self::fetch_divergence(config, env, &branch)?;
+        if divergence.kind == "no_remote_branch" {
+            return Ok(());
+        }
+        if divergence.needs_choice() {
+            return Err(divergence.into());
         }
 
+        // fetch_divergence 已获取远程分支，用系统 git 合并以便合并提交带上设备 trailer
+        if divergence.kind == "behind" || divergence.kind == "diverged" {
+            let message = Self::merge_message(config);
+            let remote_ref = format!("refs/remotes/origin/{}", branch);
+            run_git(&config.local_path, &["merge", "--no-edit", "-m", &message, &remote_ref], env, local_timeout(config))?;
+        }
+        if set_upstream {
+            let upstream = format!("--set-upstream-to=origin/{}", branch);
+            run_git(&config.local_path, &["branch", &upstream], env, local_timeout(config))?;
+        }
         Ok(())
     }
 
-    pub fn pull(&self) -> Result<()> {
-        // 使用系统 git 命令以支持 Git Credential Manager
-        let mut cmd = std::process::Command::new("git");
-        cmd.args(["pull"])
-            .current_dir(&self.config.local_path);
+    /// 当前分支在 origin 上的上游分支名
+    fn upstream_branch(local_path: &str) -> Result<Option<String>> {
+        let repo = Repository::open(local_path)?;
+        let head = match repo.head() {
+            Ok(head) if head.is_branch() => head,
+            _ => return Ok(None),
+        };
+        let branch = repo.find_branch(head.shorthand().unwrap_or(""), git2::BranchType::Local)?;
+        let Ok(upstream) = branch.upstream() else {
+            return Ok(None);
+        };
+        Ok(upstream.name()?.and_then(|n| n.strip_prefix("origin/")).map(|n| n.to_string()))
+    }
+
+    /// 获取远程分支并与本地历史比较
+    fn fetch_divergence(config: &Config, env: &[(String, String)], branch: &str) -> Result<Divergence> {
+        let heads = run_git(&config.local_path, &["ls-remote", "--heads", "origin", branch], env, network_timeout(config))?;
+        if !heads.trim().is_empty() {
+            let refspec = format!("+refs/heads/{0}:refs/remotes/origin/{0}", branch);
+            run_git(&config.local_path, &["fetch", "origin", &refspec], env, network_timeout(config))?;
+        }
+
+        let repo = Repository::open(&config.local_path)?;
+        let mut divergence = divergence::classify(&repo, branch)?;
+        if heads.trim().is_empty() {
+            // 远程分支已被删除时，本地残留的远程跟踪分支不作数
+            divergence.kind = "no_remote_branch".to_string();
+            divergence.remote = None;
+        }
+        Ok(divergence)
+    }
 
-        #[cfg(windows)]
-        cmd.creation_flags(CREATE_NO_WINDOW);
+    /// 检查本地与远程历史的关系（会执行 fetch）
+    pub fn check_divergence_at(config: &Config) -> Result<Divergence> {
+        if !config.sync_enabled() {
+            return Err(anyhow!(LOCAL_ONLY_ERROR));
+        }
+        let env = git_env(config)?;
+        let branch = match Self::prepare_upstream(config, &env)? {
+            Some(branch) => branch,
+            None => Self::upstream_branch(&config.local_path)?
+                .ok_or_else(|| anyhow!("当前分支没有对应的远程分支"))?,
+        };
+        Self::fetch_divergence(config, &env, &branch)
+    }
 
-        let output = cmd
-            .output()
-            .map_err(|e| anyhow!("无法执行 git 命令: {}", e))?;
+    /// 按用户选择处理被改写或无关的远程历史，操作前总是为本地和远程分支创建备份引用
+    pub fn resolve_divergence_at(config: &Config, choice: &str) -> Result<DivergenceResolution> {
+        let divergence = Self::check_divergence_at(config)?;
+        if !divergence.needs_choice() {
+            return Err(anyhow!("远程历史没有被改写，可以直接同步"));
+        }
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("{}", stderr.trim()));
+        let env = git_env(config)?;
+        let repo = Repository::open(&config.local_path)?;
+        let branch = divergence.branch.clone();
+        let remote_ref = format!("refs/remotes/origin/{}", branch);
+        let before = head_oid(&config.local_path);
+
+        if choice == "take_remote" {
+            // 工作区的修改不在备份引用中，避免被覆盖
+            let status = run_git(&config.local_path, &["status", "--porcelain", "--untracked-files=no"], &env, local_timeout(config))?;
+            if !status.trim().is_empty() {
+                return Err(anyhow!("有尚未提交的修改，请先保存后再采用远程历史"));
+            }
+        }
+
+        let backup_refs = divergence::create_backup_refs(&repo, &divergence)?;
+
+        match choice {
+            "force_push" => {
+                // 只覆盖检查时看到的远程提交，期间有新的推送时失败
+                let lease = format!(
+                    "--force-with-lease=refs/heads/{}:{}",
+                    branch,
+                    divergence.remote.as_deref().unwrap_or("")
+                );
+                let target = format!("HEAD:refs/heads/{}", branch);
+                run_git(&config.local_path, &["push", &lease, "origin", &target], &env, network_timeout(config))?;
+                run_git(&config.local_path, &["fetch", "origin"], &env, network_timeout(config))?;
+            }
+            "take_remote" => {
+                run_git(&config.local_path, &["reset", "--hard", &remote_ref], &env, local_timeout(config))?;
+            }
+            "merge_unrelated" => {
+                let message = Self::merge_message(config);
+                let mut args = vec!["merge", "--no-edit", "-m", &message];
+                if divergence.kind == "unrelated" {
+                    args.push("--allow-unrelated-histories");
+                }
+                args.push(&remote_ref);
+                run_git(&config.local_path, &args, &env, local_timeout(config))
+                    .map_err(|e| anyhow!("合并远程历史时发生冲突，请解决冲突后再同步: {}", e))?;
+                let target = format!("HEAD:refs/heads/{}", branch);
+                run_git(&config.local_path, &["push", "origin", &target], &env, network_timeout(config))?;
+            }
+            _ => return Err(anyhow!("未知的处理方式: {}", choice)),
+        }
+
+        let upstream = format!("--set-upstream-to=origin/{}", branch);
+        run_git(&config.local_path, &["branch", &upstream], &env, local_timeout(config))?;
+
+        let incoming = match head_oid(&config.local_path) {
+            Some(after) if before != Some(after) => Self::incoming_changes(&config.local_path, before, after)?,
+            _ => IncomingChanges::default(),
+        };
+
+        Ok(DivergenceResolution {
+            choice: choice.to_string(),
+            backup_refs,
+            incoming,
+        })
+    }
+
+    /// 系统 git 合并提交使用的提交信息（模板 + 设备 trailer）
+    fn merge_message(config: &Config) -> String {
+        commit_message::with_device_trailer(
+            config,
+            &commit_message::render(config, CommitAction::Merge, &MessageVars::default()),
+        )
+    }
+
+    /// 为此前仅在本地使用的仓库连接远程（origin 已由 init 设置）：
+    /// 远程为空时直接推送；已有历史时合并（没有共同祖先时合并无关历史），然后设置上游并推送
+    pub fn attach_remote_at(config: &Config) -> Result<AttachResult> {
+        let env = git_env(config)?;
+        let repo = Repository::open(&config.local_path)?;
+        if repo.find_remote("origin").is_err() {
+            return Err(anyhow!("未配置远程仓库"));
+        }
+
+        // 分支名与远程默认分支对齐；已有上游时沿用当前分支
+        let branch = match Self::prepare_upstream(config, &env)? {
+            Some(branch) => branch,
+            None => repo.head()?.shorthand().unwrap_or("main").to_string(),
+        };
+
+        let heads = run_git(&config.local_path, &["ls-remote", "--heads", "origin", &branch], &env, network_timeout(config))?;
+        if heads.trim().is_empty() {
+            run_git(&config.local_path, &["push", "-u", "origin", &branch], &env, network_timeout(config))?;
+            return Ok(AttachResult {
+                branch,
+                outcome: "pushed".to_string(),
+                incoming: IncomingChanges::default(),
+            });
+        }
+
+        let remote_ref = format!("refs/remotes/origin/{}", branch);
+        let refspec = format!("+refs/heads/{0}:{1}", branch, remote_ref);
+        run_git(&config.local_path, &["fetch", "origin", &refspec], &env, network_timeout(config))?;
+
+        let before = head_oid(&config.local_path);
+        let remote = repo.refname_to_id(&remote_ref)?;
+        let outcome = match before {
+            Some(local) if local == remote || repo.graph_descendant_of(local, remote)? => "up_to_date",
+            Some(local) if repo.graph_descendant_of(remote, local)? => "fast_forward",
+            Some(local) if repo.merge_base(local, remote).is_ok() => "merged",
+            Some(_) => "merged_unrelated",
+            None => "fast_forward",
+        };
+
+        if outcome != "up_to_date" {
+            let message = Self::merge_message(config);
+            let mut args = vec!["merge", "--no-edit", "-m", &message];
+            if outcome == "merged_unrelated" {
+                args.push("--allow-unrelated-histories");
+            }
+            args.push(&remote_ref);
+            if let Err(e) = run_git(&config.local_path, &args, &env, local_timeout(config)) {
+                // 仍处于仅本地模式，无法进入冲突解决流程：中止合并，恢复到连接前的状态
+                let _ = run_git(&config.local_path, &["merge", "--abort"], &[], local_timeout(config));
+                return Err(anyhow!("合并远程历史时发生冲突，已取消连接，本地数据未改变: {}", e));
+            }
+        }
+
+        let upstream = format!("--set-upstream-to=origin/{}", branch);
+        run_git(&config.local_path, &["branch", &upstream], &env, local_timeout(config))?;
+        run_git(&config.local_path, &["push", "-u", "origin", &branch], &env, network_timeout(config))?;
+
+        let incoming = match head_oid(&config.local_path) {
+            Some(after) if before != Some(after) => Self::incoming_changes(&config.local_path, before, after)?,
+            _ => IncomingChanges::default(),
+        };
+
+        Ok(AttachResult {
+            branch,
+            outcome: outcome.to_string(),
+            incoming,
+        })
+    }
+
+    /// origin 的 URL（没有 origin 时返回 None）
+    pub fn origin_url_at(local_path: &str) -> Option<String> {
+        let repo = Repository::open(local_path).ok()?;
+        let remote = repo.find_remote("origin").ok()?;
+        remote.url().map(|s| s.to_string())
+    }
+
+    /// 将 origin 恢复为 previous（None 表示删除 origin），用于连接远程失败后回滚
+    pub fn restore_origin_at(local_path: &str, previous: Option<&str>) -> Result<()> {
+        let repo = Repository::open(local_path)?;
+        match previous {
+            Some(url) => repo.remote_set_url("origin", url)?,
+            None if repo.find_remote("origin").is_ok() => repo.remote_delete("origin")?,
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// 汇总 from..to 之间的远程更改：有变化的日文件及其任务级差异
+    pub fn incoming_changes(local_path: &str, from: Option<git2::Oid>, to: git2::Oid) -> Result<IncomingChanges> {
+        let repo = Repository::open(local_path)?;
+        let old_tree = from.map(|id| repo.find_commit(id)?.tree()).transpose()?;
+        let new_tree = repo.find_commit(to)?.tree()?;
+
+        let mut changes = IncomingChanges {
+            from: from.map(|id| id.to_string()),
+            to: Some(to.to_string()),
+            ..Default::default()
+        };
+
+        // 拉取到的提交（合并提交由本地 git pull 产生，不计入）
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(to)?;
+        if let Some(from) = from {
+            revwalk.hide(from)?;
+        }
+        for oid in revwalk.flatten() {
+            let commit = repo.find_commit(oid)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            changes.commits += 1;
+            let author = commit.author().name().unwrap_or_default().to_string();
+            if !author.is_empty() && !changes.authors.contains(&author) {
+                changes.authors.push(author);
+            }
+            if let Some(device) = commit_message::device_from_message(commit.message().unwrap_or_default()) {
+                if !changes.devices.contains(&device) {
+                    changes.devices.push(device);
+                }
+            }
+        }
+
+        let diff = repo.diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), None)?;
+        let blob_text = |id: git2::Oid| {
+            repo.find_blob(id)
+                .map(|b| String::from_utf8_lossy(b.content()).to_string())
+                .unwrap_or_default()
+        };
+
+        for delta in diff.deltas() {
+            let Some(filepath) = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .and_then(|p| p.to_str())
+                .map(|p| p.replace('\\', "/"))
+            else {
+                continue;
+            };
+            if !adopt::is_day_file(&filepath) {
+                continue;
+            }
+
+            let tasks = task_parser::diff_tasks(&blob_text(delta.old_file().id()), &blob_text(delta.new_file().id()));
+            // YYYY/MM/MM-DD.md → YYYY-MM-DD
+            let date = format!("{}-{}", &filepath[..4], &filepath[8..13]);
+            changes.days.push(DayChange { date, filepath, tasks });
+        }
+
+        changes.days.sort_by(|a, b| a.date.cmp(&b.date));
+        Ok(changes)
+    }
+
+    /// 当前分支没有上游时，将本地分支名对齐为远程默认分支，返回需要设置上游的分支名
+    /// 已有上游、没有 origin 或 HEAD 不在分支上时返回 None
+    fn prepare_upstream(config: &Config, env: &[(String, String)]) -> Result<Option<String>> {
+        let repo = Repository::open(&config.local_path)?;
+        if repo.find_remote("origin").is_err() {
+            return Ok(None);
+        }
+        let current = match repo.head() {
+            Ok(head) if head.is_branch() => head.shorthand().unwrap_or("").to_string(),
+            _ => return Ok(None),
+        };
+        let mut branch = repo.find_branch(&current, git2::BranchType::Local)?;
+        if branch.upstream().is_ok() {
+            return Ok(None);
+        }
+
+        // 远程为空时没有默认分支，保留本地分支名
+        let Some(default_branch) = Self::remote_default_branch(config, env)? else {
+            return Ok(Some(current));
+        };
+
+        if default_branch != current
+            && repo.find_branch(&default_branch, git2::BranchType::Local).is_err()
+            && repo.find_branch(&format!("origin/{}", current), git2::BranchType::Remote).is_err()
+        {
+            // 重命名分支时 HEAD 会随之更新
+            branch.rename(&default_branch, false)?;
+            return Ok(Some(default_branch));
+        }
+
+        Ok(Some(current))
+    }
+
+    /// 查询远程仓库的默认分支（远程为空时返回 None）
+    fn remote_default_branch(config: &Config, env: &[(String, String)]) -> Result<Option<String>> {
+        let output = run_git(&config.local_path, &["ls-remote", "--symref", "origin", "HEAD"], env, network_timeout(config))?;
+
+        // 输出形如 "ref: refs/heads/main\tHEAD"
+        Ok(output.lines().find_map(|line| {
+            line.strip_prefix("ref: refs/heads/")
+                .and_then(|rest| rest.split('\t').next())
+                .map(|name| name.to_string())
+        }))
+    }
+
+    /// 生成静态站点并提交到 gh-pages 分支，配置了远程仓库时推送
+    pub fn publish_pages_at(config: &Config) -> Result<()> {
+        let repo = Repository::open(&config.local_path)?;
+        if repo.is_empty()? {
+            return Ok(());
+        }
+
+        let files = pages::build_site(&repo, config.pages_exclude_notes)?;
+        let signature = Signature::now(&config.user_name, &config.user_email)?;
+        let source = repo.head()?.peel_to_commit()?.id().to_string();
+        let message = commit_message::render(
+            config,
+            CommitAction::PublishPages,
+            &MessageVars { commit: Some(&source[..7]), ..Default::default() },
+        );
+        let message = commit_message::with_device_trailer(config, &message);
+        pages::publish(&repo, &files, &signature, &config.signing, local_timeout(config), &message)?;
+
+        if config.sync_enabled() {
+            // 站点内容由日文件生成，以本地为准覆盖远程分支
+            let refspec = format!("+{0}:{0}", pages::PAGES_BRANCH);
+            run_git(&config.local_path, &["push", "origin", &refspec], &git_env(config)?, network_timeout(config))?;
         }
 
         Ok(())
     }
 
+    /// 测试远程仓库连接（git ls-remote），url 为空时测试 origin
+    pub fn test_connection(config: &Config, url: Option<&str>) -> Result<()> {
+        let target = match url {
+            Some(u) if !u.is_empty() => u.to_string(),
+            _ => config.primary_remote_url().ok_or_else(|| anyhow!("未配置远程仓库"))?,
+        };
+
+        let dir = if Path::new(&config.local_path).exists() {
+            config.local_path.as_str()
+        } else {
+            "."
+        };
+
+        run_git(dir, &["ls-remote", "--heads", &target], &git_env(config)?, network_timeout(config)).map(|_| ())
+    }
+
+    /// 统计尚未推送到远程的本地提交数
+    pub fn unpushed_count(&self) -> Result<usize> {
+        if self.repo.is_empty()? {
+            return Ok(0);
+        }
+
+        let head = self.repo.head()?.peel_to_commit()?.id();
+
+        // 优先与上游分支比较
+        if let Ok(upstream) = self.repo.revparse_single("@{u}") {
+            let (ahead, _) = self.repo.graph_ahead_behind(head, upstream.id())?;
+            return Ok(ahead);
+        }
+
+        // 没有上游分支时，统计不在任何远程跟踪分支上的提交
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push(head)?;
+        for reference in self.repo.references_glob("refs/remotes/*")?.flatten() {
+            if let Some(oid) = reference.target() {
+                revwalk.hide(oid)?;
+            }
+        }
+
+        Ok(revwalk.count())
+    }
+
+    /// 统计仓库体积：对象数、包大小、最大的文件对象和各年份附件大小
+    pub fn repo_stats(&self) -> Result<RepoStats> {
+        let (loose_objects, loose_bytes, in_pack, pack_count, pack_bytes) = self.count_objects()?;
+        let mut stats = RepoStats {
+            object_count: loose_objects + in_pack,
+            loose_objects,
+            loose_bytes,
+            pack_count,
+            pack_bytes,
+            ..Default::default()
+        };
+
+        if self.repo.is_empty()? {
+            return Ok(stats);
+        }
+
+        // 所有可达对象及其路径
+        let listing = run_git(&self.config.local_path, &["rev-list", "--objects", "--all"], &[], local_timeout(&self.config))?;
+        let odb = self.repo.odb()?;
+        let mut blobs = vec![];
+        for line in listing.lines() {
+            let (oid, path) = match line.split_once(' ') {
+                Some((oid, path)) => (oid, Some(path.to_string())),
+                None => (line, None),
+            };
+            let Ok(oid) = git2::Oid::from_str(oid) else { continue };
+            if let Ok((size, git2::ObjectType::Blob)) = odb.read_header(oid) {
+                blobs.push(BlobInfo { oid: oid.to_string(), path, size: size as u64 });
+            }
+        }
+        blobs.sort_by_key(|b| std::cmp::Reverse(b.size));
+        blobs.truncate(LARGEST_BLOBS);
+        stats.largest_blobs = blobs;
+
+        let asset_regex = regex::Regex::new(r"^(\d{4})/\d{2}/assets/").unwrap();
+        let tree = self.repo.head()?.peel_to_tree()?;
+        tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+            if entry.kind() == Some(git2::ObjectType::Blob) {
+                if let Some(caps) = asset_regex.captures(dir) {
+                    let size = odb.read_header(entry.id()).map(|(size, _)| size as u64).unwrap_or(0);
+                    *stats.attachment_bytes_by_year.entry(caps[1].to_string()).or_default() += size;
+                }
+            }
+            git2::TreeWalkResult::Ok
+        })?;
+
+        Ok(stats)
+    }
+
+    /// 执行仓库维护：gc 重新打包并清除过期的不可达对象
+    /// 保留 git 默认的 gc.pruneExpire 宽限期，不会删除其他 git 进程刚写入、尚未被引用的对象；
+    /// 调用方仍需持有 git_manager 锁和同步锁，避免与前台命令或后台同步同时运行
+    pub fn run_maintenance(&self) -> Result<MaintenanceReport> {
+        let (loose_before, loose_bytes_before, in_pack_before, _, pack_bytes_before) = self.count_objects()?;
+
+        run_git(&self.config.local_path, &["gc", "--quiet"], &[], local_timeout(&self.config))?;
+
+        let (loose_after, loose_bytes_after, in_pack_after, _, pack_bytes_after) = self.count_objects()?;
+        Ok(MaintenanceReport {
+            ran_at: Local::now().to_rfc3339(),
+            bytes_before: loose_bytes_before + pack_bytes_before,
+            bytes_after: loose_bytes_after + pack_bytes_after,
+            objects_before: loose_before + in_pack_before,
+            objects_after: loose_after + in_pack_after,
+        })
+    }
+
+    /// git count-objects -v：(松散对象数, 松散字节, 包内对象数, 包数, 包字节)
+    fn count_objects(&self) -> Result<(u64, u64, u64, u64, u64)> {
+        let output = run_git(&self.config.local_path, &["count-objects", "-v"], &[], local_timeout(&self.config))?;
+        let value = |key: &str| {
+            output
+                .lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix(": "))
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .unwrap_or(0)
+        };
+
+        // size 与 size-pack 的单位为 KiB
+        Ok((
+            value("count"),
+            value("size") * 1024,
+            value("in-pack"),
+            value("packs"),
+            value("size-pack") * 1024,
+        ))
+    }
+
+    /// 按设备统计提交活动（根据提交中的 Device trailer），days 限定最近天数
+    pub fn device_activity(&self, days: Option<u32>) -> Result<Vec<DeviceActivity>> {
+        if self.repo.is_empty()? {
+            return Ok(vec![]);
+        }
+
+        let since = days.map(|d| Local::now().timestamp() - d as i64 * 24 * 60 * 60);
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push_head()?;
+        revwalk.set_sorting(Sort::TIME)?;
+
+        let mut activity: Vec<(DeviceActivity, std::collections::HashSet<String>)> = vec![];
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            let time = commit.time().seconds();
+            if since.is_some_and(|s| time < s) {
+                break;
+            }
+
+            let device = commit_message::device_from_message(commit.message().unwrap_or_default());
+            let author = commit.author().name().unwrap_or_default().to_string();
+            let timestamp = Local.timestamp_opt(time, 0).single().map(|t| t.to_rfc3339()).unwrap_or_default();
+            let day = timestamp.get(..10).unwrap_or_default().to_string();
+
+            let index = match activity.iter().position(|(a, _)| a.device == device) {
+                Some(index) => index,
+                None => {
+                    activity.push((
+                        DeviceActivity {
+                            device,
+                            commits: 0,
+                            active_days: 0,
+                            authors: vec![],
+                            first_commit: timestamp.clone(),
+                            last_commit: timestamp.clone(),
+                        },
+                        Default::default(),
+                    ));
+                    activity.len() - 1
+                }
+            };
+
+            // 按时间倒序遍历，最后一次更新的即为最早提交
+            let (entry, days) = &mut activity[index];
+            entry.commits += 1;
+            entry.first_commit = timestamp;
+            if !author.is_empty() && !entry.authors.contains(&author) {
+                entry.authors.push(author);
+            }
+            days.insert(day);
+        }
+
+        Ok(activity
+            .into_iter()
+            .map(|(mut entry, days)| {
+                entry.active_days = days.len();
+                entry
+            })
+            .collect())
+    }
+
+    /// 最近一次提交距今的时间（空仓库返回 None）
+    pub fn since_last_commit(&self) -> Option<Duration> {
+        let time = self.repo.head().ok()?.peel_to_commit().ok()?.time().seconds();
+        let elapsed = Local::now().timestamp() - time;
+        Some(Duration::from_secs(elapsed.max(0) as u64))
+    }
+
     /// 获取冲突文件列表
     pub fn get_conflict_files(&self) -> Result<Vec<String>> {
         let index = self.repo.index()?;
@@ -418,14 +1347,172 @@ impl GitManager {
         Ok((local_content, remote_content, working_content))
     }
 
-    fn get_file_at_ref(&self, refname: &str, filepath: &str) -> Result<String> {
+    /// 获取文件在指定版本（提交 ID、分支名、HEAD~1 等）的内容
+    pub fn get_file_at_ref(&self, refname: &str, filepath: &str) -> Result<String> {
+        let content = self.get_blob_at_ref(refname, filepath)?;
+        Ok(String::from_utf8(content)?)
+    }
+
+    /// 获取文件在指定版本的原始字节（用于附件等二进制文件）
+    fn get_blob_at_ref(&self, refname: &str, filepath: &str) -> Result<Vec<u8>> {
         let obj = self.repo.revparse_single(refname)?;
         let commit = obj.peel_to_commit()?;
         let tree = commit.tree()?;
         let entry = tree.get_path(Path::new(filepath))?;
         let blob = self.repo.find_blob(entry.id())?;
-        let content = std::str::from_utf8(blob.content())?.to_string();
-        Ok(content)
+        Ok(blob.content().to_vec())
+    }
+
+    /// 列出修改过指定文件的提交（最新的在前）
+    pub fn file_history(&self, filepath: &str, limit: Option<usize>) -> Result<Vec<FileCommit>> {
+        let mut history = Vec::new();
+
+        if self.repo.is_empty()? {
+            return Ok(history);
+        }
+
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push_head()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+
+        let path = Path::new(filepath);
+
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            let current = commit.tree()?.get_path(path).ok().map(|e| e.id());
+
+            // 与所有父提交都相同则说明该提交未修改此文件
+            let changed = if commit.parent_count() == 0 {
+                current.is_some()
+            } else {
+                commit.parents().all(|parent| {
+                    let previous = parent
+                        .tree()
+                        .ok()
+                        .and_then(|t| t.get_path(path).ok())
+                        .map(|e| e.id());
+                    previous != current
+                })
+            };
+
+            if !changed {
+                continue;
+            }
+
+            let author = commit.author();
+            let timestamp = author.when().seconds();
+            let time = Local
+                .timestamp_opt(timestamp, 0)
+                .single()
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default();
+
+            history.push(FileCommit {
+                id: commit.id().to_string(),
+                short_id: self.short_id(&commit.id().to_string())?,
+                author: author.name().unwrap_or_default().to_string(),
+                email: author.email().unwrap_or_default().to_string(),
+                time,
+                timestamp,
+                message: commit.message().unwrap_or_default().trim().to_string(),
+            });
+
+            if limit.is_some_and(|n| history.len() >= n) {
+                break;
+            }
+        }
+
+        Ok(history)
+    }
+
+    /// 比较文件两个版本之间的任务差异
+    /// to 为 None 时与当前工作区内容比较
+    pub fn diff_file_versions(&self, filepath: &str, from: &str, to: Option<&str>) -> Result<TaskDiff> {
+        // 文件在某个版本中不存在时视为空文件
+        let old_content = self.get_file_at_ref(from, filepath).unwrap_or_default();
+        let new_content = match to {
+            Some(refname) => self.get_file_at_ref(refname, filepath).unwrap_or_default(),
+            None => std::fs::read_to_string(Path::new(&self.config.local_path).join(filepath))
+                .unwrap_or_default(),
+        };
+
+        Ok(task_parser::diff_tasks(&old_content, &new_content))
+    }
+
+    /// 将文件恢复到指定版本并提交
+    pub fn restore_file(&self, filepath: &str, commit: &str) -> Result<()> {
+        let content = self.get_blob_at_ref(commit, filepath)
+            .map_err(|_| anyhow!("该版本中不存在文件 {}", filepath))?;
+        let short_id = self.short_id(commit)?;
+
+        let full_path = Path::new(&self.config.local_path).join(filepath);
+        if let Some(parent) = full_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&full_path, content)?;
+
+        let message = self.commit_message(
+            CommitAction::RestoreFile,
+            &MessageVars { file: Some(filepath), commit: Some(&short_id), ..Default::default() },
+        );
+        self.add_and_commit(filepath, &message)
+    }
+
+    /// 从指定版本中找回单个任务（含子任务），插入到当前文件的原区域中
+    pub fn restore_task(&self, filepath: &str, commit: &str, text: &str) -> Result<()> {
+        let old_content = self.get_file_at_ref(commit, filepath)
+            .map_err(|_| anyhow!("该版本中不存在文件 {}", filepath))?;
+
+        let task = task_parser::parse_tasks(&old_content)
+            .into_iter()
+            .find(|t| t.text == text)
+            .ok_or_else(|| anyhow!("该版本中找不到任务: {}", text))?;
+
+        self.insert_task_and_commit(filepath, &task.section, &task.lines, text)
+    }
+
+    /// 将任务行插入当前文件并提交（任务已存在时报错）
+    pub fn insert_task_and_commit(
+        &self,
+        filepath: &str,
+        section: &str,
+        lines: &[String],
+        text: &str,
+    ) -> Result<()> {
+        let full_path = Path::new(&self.config.local_path).join(filepath);
+        let current = std::fs::read_to_string(&full_path).unwrap_or_default();
+
+        if task_parser::parse_tasks(&current).iter().any(|t| t.text == text) {
+            return Err(anyhow!("任务已存在: {}", text));
+        }
+
+        let new_content = task_parser::insert_task(&current, section, lines);
+        if let Some(parent) = full_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&full_path, new_content)?;
+
+        let message = self.commit_message(
+            CommitAction::RestoreTask,
+            &MessageVars { file: Some(filepath), task: Some(text), ..Default::default() },
+        );
+        self.add_and_commit(filepath, &message)
+    }
+
+    /// 按配置的模板生成指定操作的提交信息
+    pub fn commit_message(&self, action: CommitAction, vars: &MessageVars) -> String {
+        commit_message::render(&self.config, action, vars)
+    }
+
+    /// 获取当前 HEAD 提交 ID
+    pub fn head_commit_id(&self) -> Result<String> {
+        Ok(self.repo.head()?.peel_to_commit()?.id().to_string())
+    }
+
+    fn short_id(&self, refname: &str) -> Result<String> {
+        let commit = self.repo.revparse_single(refname)?.peel_to_commit()?;
+        let short_id = commit.as_object().short_id()?;
+        Ok(short_id.as_str().unwrap_or_default().to_string())
     }
 
     /// 解决冲突：使用提供的内容覆盖文件并标记为已解决
@@ -473,7 +1560,7 @@ impl GitManager {
         let merge_head_oid = git2::Oid::from_str(merge_head_str.trim())?;
         let merge_commit = self.repo.find_commit(merge_head_oid)?;
 
-        self.repo.commit(
+        self.commit_to(
             Some("HEAD"),
             &signature,
             &signature,
@@ -487,4 +1574,691 @@ impl GitManager {
 
         Ok(())
     }
+
+    /// 诊断仓库状态：残留的 index.lock、游离 HEAD、未设置上游、未完成的合并、
+    /// 被跟踪的 .desktop_data、缺少 .gitignore 规则；verify_objects 为 true 时
+    /// 还会执行 git fsck 检查损坏的对象（大仓库耗时较长，只在修复或用户要求时执行）
+    pub fn check_health(&self, verify_objects: bool) -> Result<Vec<HealthIssue>> {
+        let mut issues = vec![];
+
+        if let Some(age) = self.index_lock_age() {
+            let stale = age >= STALE_LOCK_AGE;
+            issues.push(HealthIssue {
+                kind: "index_lock".to_string(),
+                severity: "error".to_string(),
+                message: if stale {
+                    format!("存在残留的 .git/index.lock（{} 秒前创建），git 操作会失败", age.as_secs())
+                } else {
+                    "存在 .git/index.lock，可能有 git 命令正在运行，请稍后再检查".to_string()
+                },
+                fixable: stale,
+            });
+        }
+
+        if self.repo.is_empty()? {
+            return Ok(issues);
+        }
+
+        match self.repo.state() {
+            git2::RepositoryState::Clean => {}
+            git2::RepositoryState::Merge => {
+                let conflicts = self.has_conflicts()?;
+                issues.push(HealthIssue {
+                    kind: "merge_in_progress".to_string(),
+                    severity: "error".to_string(),
+                    message: if conflicts {
+                        "合并未完成且有未解决的冲突，请先在冲突界面解决".to_string()
+                    } else {
+                        "合并未完成（冲突已解决但未提交）".to_string()
+                    },
+                    fixable: !conflicts,
+                });
+            }
+            state => issues.push(HealthIssue {
+                kind: "operation_in_progress".to_string(),
+                severity: "error".to_string(),
+                message: format!(
+                    "有未完成的 git 操作（{:?}），请在命令行中继续或中止（如 git rebase --abort）",
+                    state
+                ),
+                fixable: false,
+            }),
+        }
+
+        if self.repo.head_detached()? {
+            let fixable = self.detached_head_target()?.is_some();
+            issues.push(HealthIssue {
+                kind: "detached_head".to_string(),
+                severity: "error".to_string(),
+                message: if fixable {
+                    "HEAD 处于游离状态，新提交不会出现在任何分支上".to_string()
+                } else {
+                    "HEAD 处于游离状态且与本地分支已分叉，需要手动处理".to_string()
+                },
+                fixable,
+            });
+        } else if self.config.sync_enabled() && self.repo.find_remote("origin").is_ok() {
+            let head = self.repo.head()?;
+            let branch_name = head.shorthand().unwrap_or("").to_string();
+            let branch = self.repo.find_branch(&branch_name, git2::BranchType::Local)?;
+
+            if branch.upstream().is_err() {
+                let fixable = self
+                    .repo
+                    .find_branch(&format!("origin/{}", branch_name), git2::BranchType::Remote)
+                    .is_ok();
+                issues.push(HealthIssue {
+                    kind: "no_upstream".to_string(),
+                    severity: "warning".to_string(),
+                    message: if fixable {
+                        format!("分支 {} 未设置上游分支，拉取和推送可能失败", branch_name)
+                    } else {
+                        format!("分支 {} 未设置上游分支，远程仓库中还没有该分支，请先推送", branch_name)
+                    },
+                    fixable,
+                });
+            }
+        }
+
+        let index = self.repo.index()?;
+        if index
+            .iter()
+            .any(|e| String::from_utf8_lossy(&e.path).starts_with(".desktop_data/"))
+        {
+            issues.push(HealthIssue {
+                kind: "desktop_data_tracked".to_string(),
+                severity: "warning".to_string(),
+                message: ".desktop_data 被 git 跟踪，本地客户端数据会被同步到其他设备".to_string(),
+                fixable: true,
+            });
+        }
+
+        let gitignore_path = Path::new(&self.config.local_path).join(".gitignore");
+        let gitignore = std::fs::read_to_string(&gitignore_path).unwrap_or_default();
+        if !has_desktop_data_rule(&gitignore) {
+            issues.push(HealthIssue {
+                kind: "gitignore_rule_missing".to_string(),
+                severity: "warning".to_string(),
+                message: ".gitignore 中缺少 .desktop_data/ 规则".to_string(),
+                fixable: true,
+            });
+        }
+
+        if verify_objects {
+            if let Err(e) = run_git(&self.config.local_path, &["fsck", "--no-dangling", "--no-progress"], &[], local_timeout(&self.config)) {
+                issues.push(HealthIssue {
+                    kind: "corrupted_objects".to_string(),
+                    severity: "error".to_string(),
+                    message: format!("仓库对象损坏，建议从远程重新克隆: {}", e),
+                    fixable: false,
+                });
+            }
+        }
+
+        Ok(issues)
+    }
+
+    /// 修复可以安全处理的问题，返回修复记录和剩余问题
+    pub fn repair(&self) -> Result<RepairReport> {
+        let mut report = RepairReport::default();
+        let mut attempted: Vec<String> = vec![];
+
+        // 修复后可能暴露新的问题（如 HEAD 回到分支后才能检查上游），最多检查三轮
+        for _ in 0..3 {
+            let pending: Vec<HealthIssue> = self
+                .check_health(false)?
+                .into_iter()
+                .filter(|i| i.fixable && !attempted.contains(&i.kind))
+                .collect();
+            if pending.is_empty() {
+                break;
+            }
+            for issue in pending {
+                attempted.push(issue.kind.clone());
+                if let Some(message) = self.repair_issue(&issue) {
+                    report.fixed.push(message);
+                }
+            }
+        }
+
+        // 损坏的对象无法自动修复，只在最后检查一次
+        report.remaining = self.check_health(true)?;
+        Ok(report)
+    }
+
+    /// 修复单个问题，成功时返回说明
+    fn repair_issue(&self, issue: &HealthIssue) -> Option<String> {
+        let result = match issue.kind.as_str() {
+            "index_lock" => self.remove_index_lock(),
+            "merge_in_progress" => self.finish_merge(),
+            "detached_head" => self.reattach_head(),
+            "no_upstream" => self.set_default_upstream(),
+            "desktop_data_tracked" => self
+                .untrack_desktop_data()
+                .map(|_| "已停止跟踪 .desktop_data（本地文件保留）".to_string()),
+            "gitignore_rule_missing" => self
+                .ensure_gitignore()
+                .map(|_| "已在 .gitignore 中添加 .desktop_data/ 规则".to_string()),
+            _ => return None,
+        };
+
+        match result {
+            Ok(message) => Some(message),
+            Err(e) => {
+                eprintln!("修复 {} 失败: {}", issue.kind, e);
+                None
+            }
+        }
+    }
+
+    /// index.lock 存在时返回其存在时长
+    fn index_lock_age(&self) -> Option<Duration> {
+        let lock_path = self.repo.path().join("index.lock");
+        let modified = std::fs::metadata(lock_path).ok()?.modified().ok()?;
+        Some(modified.elapsed().unwrap_or_default())
+    }
+
+    fn remove_index_lock(&self) -> Result<String> {
+        std::fs::remove_file(self.repo.path().join("index.lock"))?;
+        Ok("已删除残留的 .git/index.lock".to_string())
+    }
+
+    /// 完成冲突已解决的合并；MERGE_HEAD 已包含在 HEAD 中时只清理合并状态
+    fn finish_merge(&self) -> Result<String> {
+        let merge_head_str = std::fs::read_to_string(self.repo.path().join("MERGE_HEAD"))?;
+        let merge_head = git2::Oid::from_str(merge_head_str.lines().next().unwrap_or("").trim())?;
+        let head = self.repo.head()?.peel_to_commit()?.id();
+
+        if head == merge_head || self.repo.graph_descendant_of(head, merge_head)? {
+            self.repo.cleanup_state()?;
+            return Ok("已清理残留的合并状态".to_string());
+        }
+
+        self.complete_merge(&self.commit_message(CommitAction::Merge, &MessageVars::default()))?;
+        Ok("已提交冲突已解决的合并".to_string())
+    }
+
+    /// 游离 HEAD 可以安全回到的本地分支及其处理方式
+    fn detached_head_target(&self) -> Result<Option<(String, DetachedFix)>> {
+        let head = self.repo.head()?.peel_to_commit()?.id();
+
+        // 优先检查与远程默认分支同名的分支，其次 main / master
+        let mut names: Vec<String> = vec![];
+        if let Ok(origin_head) = self.repo.find_reference("refs/remotes/origin/HEAD") {
+            if let Some(target) = origin_head.symbolic_target() {
+                names.push(target.trim_start_matches("refs/remotes/origin/").to_string());
+            }
+        }
+        names.push("main".to_string());
+        names.push("master".to_string());
+        for branch in self.repo.branches(Some(git2::BranchType::Local))?.flatten() {
+            if let Some(name) = branch.0.name()? {
+                names.push(name.to_string());
+            }
+        }
+
+        let mut has_branch = false;
+        for name in names {
+            let Ok(branch) = self.repo.find_branch(&name, git2::BranchType::Local) else {
+                continue;
+            };
+            has_branch = true;
+            let Some(tip) = branch.get().target() else {
+                continue;
+            };
+
+            if tip == head {
+                return Ok(Some((name, DetachedFix::Attach)));
+            }
+            if self.repo.graph_descendant_of(head, tip)? {
+                return Ok(Some((name, DetachedFix::FastForwardBranch)));
+            }
+            if self.repo.graph_descendant_of(tip, head)? {
+                return Ok(Some((name, DetachedFix::Checkout)));
+            }
+        }
+
+        // 没有任何本地分支时在当前提交上创建 main
+        if !has_branch {
+            return Ok(Some(("main".to_string(), DetachedFix::CreateBranch)));
+        }
+
+        Ok(None)
+    }
+
+    /// 让 HEAD 回到本地分支，不丢失任何提交
+    fn reattach_head(&self) -> Result<String> {
+        let (name, fix) = self
+            .detached_head_target()?
+            .ok_or_else(|| anyhow!("游离 HEAD 与本地分支已分叉"))?;
+        let head = self.repo.head()?.peel_to_commit()?;
+        let refname = format!("refs/heads/{}", name);
+
+        let message = match fix {
+            DetachedFix::Attach => format!("已切换回分支 {}", name),
+            DetachedFix::FastForwardBranch => {
+                // HEAD 领先于分支：把分支移动到 HEAD，工作区不变
+                self.repo.reference(&refname, head.id(), true, "repair: 游离 HEAD 快进分支")?;
+                format!("已将分支 {} 快进到游离 HEAD 并切换回该分支", name)
+            }
+            DetachedFix::Checkout => {
+                // 分支领先于 HEAD：安全检出（有冲突的本地修改时失败）
+                let target = self.repo.find_reference(&refname)?.peel_to_commit()?;
+                let mut checkout = git2::build::CheckoutBuilder::new();
+                checkout.safe();
+                self.repo.checkout_tree(target.as_object(), Some(&mut checkout))?;
+                format!("已检出分支 {}（该分支包含游离 HEAD 的全部提交）", name)
+            }
+            DetachedFix::CreateBranch => {
+                self.repo.branch(&name, &head, false)?;
+                format!("已在当前提交上创建分支 {}", name)
+            }
+        };
+
+        self.repo.set_head(&refname)?;
+        Ok(message)
+    }
+
+    /// 将当前分支的上游设置为 origin 上的同名分支
+    fn set_default_upstream(&self) -> Result<String> {
+        let head = self.repo.head()?;
+        let branch_name = head.shorthand().unwrap_or("").to_string();
+        let mut branch = self.repo.find_branch(&branch_name, git2::BranchType::Local)?;
+        let upstream = format!("origin/{}", branch_name);
+        branch.set_upstream(Some(&upstream))?;
+        Ok(format!("已将分支 {} 的上游设置为 {}", branch_name, upstream))
+    }
+}
+
+/// 超过该时长的 index.lock 视为残留
+const STALE_LOCK_AGE: Duration = Duration::from_secs(60);
+
+/// 游离 HEAD 的修复方式
+enum DetachedFix {
+    /// 分支与 HEAD 指向同一提交
+    Attach,
+    /// HEAD 领先于分支
+    FastForwardBranch,
+    /// 分支领先于 HEAD
+    Checkout,
+    /// 没有本地分支
+    CreateBranch,
+}
+
+/// 克隆进度事件的最小间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+fn percent(current: usize, total: usize) -> u32 {
+    (current * 100).checked_div(total).unwrap_or(0) as u32
+}
+
+/// git2 远程操作的认证回调：HTTPS 使用令牌，SSH 依次尝试配置的私钥、ssh-agent 和默认私钥
+fn remote_callbacks<'a>(
+    https_credentials: Option<(String, String)>,
+    ssh_settings: &SshSettings,
+) -> git2::RemoteCallbacks<'a> {
+    let mut callbacks = git2::RemoteCallbacks::new();
+    let ssh_settings = ssh_settings.clone();
+    let mut attempts = 0;
+
+    callbacks.credentials(move |url, username_from_url, allowed| {
+        attempts += 1;
+        let username = username_from_url.unwrap_or("git");
+
+        if allowed.contains(git2::CredentialType::SSH_KEY) {
+            // 第一次使用配置的私钥（未配置时使用 ssh-agent），之后依次尝试默认私钥
+            if attempts == 1 {
+                return match &ssh_settings.key_path {
+                    Some(key) => git2::Cred::ssh_key(
+                        username,
+                        None,
+                        Path::new(key),
+                        ssh_settings.passphrase.as_deref(),
+                    ),
+                    None => git2::Cred::ssh_key_from_agent(username),
+                };
+            }
+            if ssh_settings.key_path.is_none() {
+                if let Some(key) = ssh::default_identity_files().get(attempts - 2) {
+                    return git2::Cred::ssh_key(username, None, key, ssh_settings.passphrase.as_deref());
+                }
+            }
+            return Err(git2::Error::from_str("SSH 认证失败，请检查私钥是否已添加到平台"));
+        }
+
+        if allowed.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            if attempts > 1 {
+                return Err(git2::Error::from_str("认证失败，请检查访问令牌"));
+            }
+            return match &https_credentials {
+                Some((user, password)) => git2::Cred::userpass_plaintext(user, password),
+                // 未提供令牌时使用系统凭据助手（如 Git Credential Manager）
+                None => git2::Cred::credential_helper(&git2::Config::open_default()?, url, username_from_url),
+            };
+        }
+
+        if allowed.contains(git2::CredentialType::USERNAME) {
+            return git2::Cred::username(username);
+        }
+
+        git2::Cred::default()
+    });
+
+    // 指定了 known_hosts 文件时自行校验主机公钥，否则交给 libgit2 使用 ~/.ssh/known_hosts
+    let known_hosts_path = ssh_settings.known_hosts_path.clone();
+    callbacks.certificate_check(move |cert, host| {
+        let (Some(path), Some(hostkey)) = (&known_hosts_path, cert.as_hostkey()) else {
+            return Ok(git2::CertificateCheckStatus::CertificatePassthrough);
+        };
+        match hostkey.hostkey() {
+            Some(key) if ssh::is_known_host(path, host, key) => Ok(git2::CertificateCheckStatus::CertificateOk),
+            _ => Err(git2::Error::from_str(&format!("主机 {} 的公钥不在 known_hosts 中", host))),
+        }
+    });
+
+    callbacks
+}
+
+/// 检查 .gitignore 内容是否已包含 .desktop_data/ 规则
+fn has_desktop_data_rule(content: &str) -> bool {
+    content.lines().any(|line| {
+        let trimmed = line.trim();
+        trimmed == ".desktop_data/" || trimmed == ".desktop_data"
+    })
+}
+
+/// 按配置生成 git 命令的环境变量（SSH 密钥、口令、known_hosts，HTTPS 令牌）
+fn git_env(config: &Config) -> Result<Vec<(String, String)>> {
+    let mut env = SshSettings::from_config(config).git_env()?;
+
+    if let Some(token) = config.token.as_deref().filter(|t| !t.is_empty()) {
+        let (username, password) = provider::provider_for(&config.git_provider, None).credentials(token);
+        env.extend(credentials::https_env(&username, &password)?);
+    }
+
+    Ok(env)
+}
+
+/// 当前 HEAD 指向的提交（空仓库或无法打开时为 None）
+fn head_oid(local_path: &str) -> Option<git2::Oid> {
+    Repository::open(local_path).ok()?.head().ok()?.target()
+}
+
+/// git 命令超时（已终止进程）
+#[derive(Debug, Clone)]
+pub struct GitTimeout {
+    /// 执行的 git 子命令，如 push
+    pub command: String,
+    pub secs: u64,
+}
+
+impl std::fmt::Display for GitTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "git {} 超时（{} 秒未完成），已终止。请检查网络连接和凭据设置",
+            self.command, self.secs
+        )
+    }
+}
+
+impl std::error::Error for GitTimeout {}
+
+/// 网络 git 命令（拉取、推送、ls-remote 等）的超时
+fn network_timeout(config: &Config) -> Option<Duration> {
+    Some(config.git_timeout_secs)
+        .filter(|&secs| secs > 0)
+        .map(|secs| Duration::from_secs(secs as u64))
+}
+
+/// 本地 git 命令（add、gc、fsck 等）以及钩子、签名程序的超时
+pub fn local_timeout(config: &Config) -> Option<Duration> {
+    Some(config.git_local_timeout_secs)
+        .filter(|&secs| secs > 0)
+        .map(|secs| Duration::from_secs(secs as u64))
+}
+
+/// 执行系统 git 命令（以支持 Git Credential Manager），返回标准输出
+/// 禁止终端交互提示；超过 timeout 时终止 git 及其子进程（ssh、凭据助手等），
+/// 清理被终止的命令留下的锁文件和未完成的合并，并返回 GitTimeout
+fn run_git(dir: &str, args: &[&str], env: &[(String, String)], timeout: Option<Duration>) -> Result<String> {
+    let mut cmd = std::process::Command::new("git");
+    cmd.args(args)
+        .current_dir(dir)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .envs(env.iter().map(|(k, v)| (k, v)));
+
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let snapshot = timeout.and_then(|_| RepoSnapshot::take(dir));
+    let output = process::output_with_timeout(&mut cmd, None, timeout)
+        .map_err(|e| anyhow!("无法执行 git 命令: {}", e))?;
+
+    let Some(output) = output else {
+        if let Some(snapshot) = snapshot {
+            snapshot.cleanup(dir, timeout);
+        }
+        return Err(GitTimeout {
+            command: args.first().unwrap_or(&"").to_string(),
+            secs: timeout.map(|t| t.as_secs()).unwrap_or_default(),
+        }
+        .into());
+    };
+
+    if !output.status.success() {
+        // 合并冲突等信息输出在标准输出中
+        let detail = if output.stderr.iter().all(u8::is_ascii_whitespace) { &output.stdout } else { &output.stderr };
+        let detail = String::from_utf8_lossy(detail);
+        return Err(anyhow!("{}", credentials::redact(detail.trim(), env)));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// 执行 git 命令前仓库中已有的锁文件和未完成的操作，命令超时被终止后只清理它自己留下的部分
+struct RepoSnapshot {
+    git_dir: std::path::PathBuf,
+    locks: Vec<std::path::PathBuf>,
+    merging: bool,
+    rebasing: bool,
+}
+
+impl RepoSnapshot {
+    fn take(dir: &str) -> Option<Self> {
+        let git_dir = Repository::open(dir).ok()?.path().to_path_buf();
+        Some(Self {
+            locks: lock_files(&git_dir),
+            merging: git_dir.join("MERGE_HEAD").exists(),
+            rebasing: is_rebasing(&git_dir),
+            git_dir,
+        })
+    }
+
+    fn cleanup(&self, dir: &str, timeout: Option<Duration>) {
+        for lock in lock_files(&self.git_dir) {
+            if !self.locks.contains(&lock) {
+                let _ = std::fs::remove_file(&lock);
+            }
+        }
+
+        // 中止被打断的合并或变基（pull 可能按 pull.rebase 配置执行变基）
+        if !self.merging && self.git_dir.join("MERGE_HEAD").exists() {
+            let _ = run_git(dir, &["merge", "--abort"], &[], timeout);
+        }
+        if !self.rebasing && is_rebasing(&self.git_dir) {
+            let _ = run_git(dir, &["rebase", "--abort"], &[], timeout);
+        }
+    }
+}
+
+fn is_rebasing(git_dir: &Path) -> bool {
+    git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists()
+}
+
+/// .git 中的 *.lock 文件（index.lock、引用锁等，不含对象库）
+fn lock_files(git_dir: &Path) -> Vec<std::path::PathBuf> {
+    let mut locks = vec![];
+    let mut dirs = vec![git_dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if entry.file_name() != "objects" {
+                    dirs.push(path);
+                }
+            } else if path.extension().is_some_and(|ext| ext == "lock") {
+                locks.push(path);
+            }
+        }
+    }
+
+    locks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn killed_git_command_leaves_no_locks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("todo-run-git-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_string_lossy().to_string();
+        let timeout = Some(Duration::from_secs(1));
+
+        run_git(&path, &["init", "-q"], &[], timeout).unwrap();
+        std::fs::write(dir.join("a.md"), "a").unwrap();
+        run_git(&path, &["add", "a.md"], &[], timeout).unwrap();
+
+        // 已有的锁文件不属于被终止的命令，保留
+        let foreign_lock = dir.join(".git").join("refs").join("heads").join("other.lock");
+        std::fs::write(&foreign_lock, "").unwrap();
+
+        // git commit 持有 index.lock 时执行卡住的钩子
+        let hook = dir.join(".git").join("hooks").join("pre-commit");
+        std::fs::write(&hook, "#!/bin/sh\nsleep 30\n").unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let started = std::time::Instant::now();
+        let error = run_git(&path, &["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-qm", "a"], &[], timeout)
+            .unwrap_err();
+        assert!(error.downcast_ref::<GitTimeout>().is_some(), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(!dir.join(".git").join("index.lock").exists());
+        assert!(foreign_lock.exists());
+
+        // 同一钩子由应用直接执行时同样会被终止
+        let repo = Repository::open(&dir).unwrap();
+        let error = hooks::run_hook(&repo, "pre-commit", &[], timeout).unwrap_err();
+        assert!(error.to_string().contains("超时"), "{}", error);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn merges_and_hooked_commits_carry_device_trailer() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = std::env::temp_dir().join(format!("todo-device-trailer-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let git = |dir: &Path, args: &[&str]| {
+            let mut full = vec!["-c", "user.name=Other", "-c", "user.email=other@example.com"];
+            full.extend_from_slice(args);
+            run_git(&dir.to_string_lossy(), &full, &[], None).unwrap()
+        };
+        git(&root, &["init", "--bare", "-q", "-b", "main", "remote.git"]);
+
+        let config = Config {
+            local_path: root.join("local").to_string_lossy().to_string(),
+            user_name: "Test".to_string(),
+            user_email: "test@example.com".to_string(),
+            remote_url: Some(root.join("remote.git").to_string_lossy().to_string()),
+            device_name: "Laptop".to_string(),
+            run_git_hooks: true,
+            ..Default::default()
+        };
+        let git_mgr = GitManager::new(config.clone()).unwrap();
+        git_mgr.init().unwrap();
+        let write = |name: &str| std::fs::write(root.join("local").join(name), name).unwrap();
+        write("a.md");
+        git_mgr.add_and_commit("a.md", "a").unwrap();
+        git_mgr.push().unwrap();
+
+        // 另一台设备推送，本地同时有新提交：拉取时产生合并提交
+        git(&root, &["clone", "-q", "remote.git", "other"]);
+        std::fs::write(root.join("other").join("b.md"), "b").unwrap();
+        git(&root.join("other"), &["add", "b.md"]);
+        git(&root.join("other"), &["commit", "-qm", "b"]);
+        git(&root.join("other"), &["push", "-q"]);
+        write("c.md");
+        git_mgr.add_and_commit("c.md", "c").unwrap();
+
+        git_mgr.pull().unwrap();
+        let local = root.join("local");
+        assert_eq!(git(&local, &["rev-list", "--count", "--merges", "HEAD"]), "1\n");
+        let merge = git(&local, &["log", "-1", "--format=%B"]);
+        assert!(merge.contains("Device: Laptop"), "{}", merge);
+
+        // commit-msg 钩子看到的信息已包含设备 trailer
+        let hook = local.join(".git").join("hooks").join("commit-msg");
+        std::fs::write(&hook, "#!/bin/sh\ngrep -q '^Device: Laptop$' \"$1\" || { echo missing trailer; exit 1; }\n").unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+        write("d.md");
+        git_mgr.add_and_commit("d.md", "d").unwrap();
+        let message = git(&local, &["log", "-1", "--format=%B"]);
+        assert_eq!(message.matches("Device: Laptop").count(), 1, "{}", message);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn import_commits_only_scanned_files() {
+        let root = std::env::temp_dir().join(format!("todo-import-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("2026").join("10")).unwrap();
+        std::fs::write(root.join("2026").join("10").join("10-17.md"), "- [ ] a").unwrap();
+        std::fs::write(root.join("2026-10-18.md"), "- [ ] b").unwrap();
+        std::fs::write(root.join("notes.md"), "notes").unwrap();
+        std::fs::write(root.join("secret.txt"), "token").unwrap();
+        std::fs::create_dir_all(root.join("2026").join("10").join("assets")).unwrap();
+        std::fs::write(root.join("2026").join("10").join("assets").join("a.png"), "png").unwrap();
+
+        let config = Config {
+            local_path: root.to_string_lossy().to_string(),
+            user_name: "Test".to_string(),
+            user_email: "test@example.com".to_string(),
+            ..Default::default()
+        };
+        let scan = adopt::scan_folder(&config.local_path).unwrap();
+        let renamed = adopt::convert_near_matches(&config.local_path, &scan).unwrap();
+        let paths = adopt::import_paths(&scan, &renamed);
+        let removed: Vec<String> = renamed.iter().map(|r| r.from.clone()).collect();
+
+        let git_mgr = GitManager::new(config.clone()).unwrap();
+        let (_, count) = git_mgr.import_all(&paths, &removed, "import").unwrap().unwrap();
+        assert_eq!(count, 5);
+
+        let files = run_git(&config.local_path, &["ls-tree", "-r", "--name-only", "HEAD"], &[], None).unwrap();
+        assert_eq!(
+            files.lines().collect::<Vec<_>>(),
+            [".gitignore", "2026/10/10-17.md", "2026/10/10-18.md", "2026/10/assets/a.png", "notes.md"]
+        );
+        assert!(root.join("secret.txt").exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
@@ -0,0 +1,150 @@
+use anyhow::{Result, anyhow};
+use git2::Repository;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::process;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// 钩子目录：core.hooksPath（相对路径相对于工作区）或 .git/hooks
+fn hooks_dir(repo: &Repository) -> PathBuf {
+    let configured = repo
+        .config()
+        .and_then(|c| c.get_path("core.hooksPath"))
+        .ok();
+
+    match (configured, repo.workdir()) {
+        (Some(path), Some(workdir)) if path.is_relative() => workdir.join(path),
+        (Some(path), _) => path,
+        (None, _) => repo.path().join("hooks"),
+    }
+}
+
+/// 已安装且可执行的钩子
+fn find_hook(repo: &Repository, name: &str) -> Option<PathBuf> {
+    let path = hooks_dir(repo).join(name);
+    if !path.is_file() {
+        return None;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).ok()?.permissions().mode();
+        if mode & 0o111 == 0 {
+            return None;
+        }
+    }
+
+    Some(path)
+}
+
+/// 执行钩子（未安装时直接返回），钩子以非零状态退出时返回其输出
+/// 超过 timeout 时终止钩子及其子进程
+pub fn run_hook(repo: &Repository, name: &str, args: &[&str], timeout: Option<Duration>) -> Result<()> {
+    let Some(hook) = find_hook(repo, name) else {
+        return Ok(());
+    };
+    let workdir = repo.workdir().unwrap_or_else(|| repo.path());
+
+    // Windows 上钩子是 shell 脚本，通过 Git for Windows 自带的 sh 执行；
+    // 找不到 sh 时报错而不是静默跳过（调用方仅在开启执行钩子时调用）
+    #[cfg(windows)]
+    let mut cmd = {
+        let sh = windows_sh().ok_or_else(|| {
+            anyhow!("找不到 Git for Windows 的 sh.exe，无法执行 {} 钩子（可在设置中关闭“执行仓库 Git 钩子”）", name)
+        })?;
+        let mut cmd = std::process::Command::new(sh);
+        cmd.arg(hook.to_string_lossy().replace('\\', "/"));
+        cmd.creation_flags(CREATE_NO_WINDOW);
+        cmd
+    };
+    #[cfg(not(windows))]
+    let mut cmd = std::process::Command::new(&hook);
+
+    cmd.args(args)
+        .current_dir(workdir)
+        .env("GIT_INDEX_FILE", repo.path().join("index"))
+        .env("GIT_TERMINAL_PROMPT", "0");
+
+    let output = process::output_with_timeout(&mut cmd, None, timeout)
+        .map_err(|e| anyhow!("无法执行 {} 钩子: {}", name, e))?
+        .ok_or_else(|| {
+            let secs = timeout.map(|t| t.as_secs()).unwrap_or_default();
+            anyhow!("{} 钩子超时（{} 秒未完成），已终止", name, secs)
+        })?;
+
+    if !output.status.success() {
+        let mut message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if message.is_empty() {
+            message = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        }
+        return Err(anyhow!("{} 钩子拒绝了提交: {}", name, message));
+    }
+
+    Ok(())
+}
+
+/// 执行 commit-msg 钩子，返回钩子处理后的提交信息
+pub fn run_commit_msg_hook(repo: &Repository, message: &str, timeout: Option<Duration>) -> Result<String> {
+    if find_hook(repo, "commit-msg").is_none() {
+        return Ok(message.to_string());
+    }
+
+    let message_path = repo.path().join("COMMIT_EDITMSG");
+    std::fs::write(&message_path, format!("{}\n", message))?;
+    run_hook(repo, "commit-msg", &[&path_arg(&message_path)], timeout)?;
+
+    // 与 git 的默认清理方式一致：去掉注释行和末尾空白
+    let edited = std::fs::read_to_string(&message_path)?;
+    let cleaned = edited
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string();
+
+    if cleaned.is_empty() {
+        return Err(anyhow!("commit-msg 钩子清空了提交信息"));
+    }
+    Ok(cleaned)
+}
+
+/// Git for Windows 自带的 sh.exe（通常不在 PATH 中）：根据 git --exec-path 定位安装目录，
+/// 找不到时再查找 PATH
+#[cfg(windows)]
+fn windows_sh() -> Option<PathBuf> {
+    static SH: std::sync::OnceLock<Option<PathBuf>> = std::sync::OnceLock::new();
+    SH.get_or_init(|| {
+        let exec_path = std::process::Command::new("git")
+            .arg("--exec-path")
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()));
+
+        // exec-path 形如 <安装目录>/mingw64/libexec/git-core
+        let bundled = exec_path
+            .iter()
+            .flat_map(|path| path.ancestors().skip(3).take(1))
+            .flat_map(|root| [root.join("bin").join("sh.exe"), root.join("usr").join("bin").join("sh.exe")]);
+
+        let on_path = std::env::var_os("PATH")
+            .map(|paths| std::env::split_paths(&paths).map(|dir| dir.join("sh.exe")).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        bundled.chain(on_path).find(|path| path.is_file())
+    })
+    .clone()
+}
+
+fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
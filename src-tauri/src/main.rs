@@ -4,11 +4,33 @@
 mod git_manager;
 mod file_manager;
 mod config;
+mod task_parser;
+mod trash;
+mod sync_queue;
+mod ssh;
+mod secrets;
+mod credentials;
+mod provider;
+mod remote_api;
+mod pages;
+mod adopt;
+mod hooks;
+mod signing;
+mod commit_message;
+mod maintenance;
+mod divergence;
+mod process;
 
 use git_manager::GitManager;
+use commit_message::{CommitAction, MessageVars};
+use divergence::{Divergence, DivergenceResolution, PullError};
+use signing::CommitError;
+use ssh::SshSettings;
+use secrets::SecretStore;
 use file_manager::FileManager;
-use config::{Config, GitInfo, DataPointer, Statistics, DailyStats, StatsSummary, PastUncompleted, PastUncompletedTask};
-use std::sync::Mutex;
+use config::{Config, GitInfo, DataPointer, Statistics, DailyStats, StatsSummary, PastUncompleted, PastUncompletedTask, FileCommit, TaskDiff, TrashEntry, SyncStatus, RemoteStatus, HealthIssue, RepairReport, CloneProgress, FolderScan, AdoptResult, SigningConfig, SshHostKey, RepoStats, MaintenanceReport, IncomingChanges, PushResult, DeviceActivity, AttachResult};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::path::Path;
 use std::collections::HashMap;
 use std::fs;
@@ -19,38 +41,52 @@ use tauri::{
     SystemTrayMenuItem, WindowEvent,
 };
 
-#[cfg(windows)]
-use std::os::windows::process::CommandExt;
-
-#[cfg(windows)]
-const CREATE_NO_WINDOW: u32 = 0x08000000;
+/// 在后台线程中异步执行 git 同步（不阻塞 UI），拉取到远程更改时发送 incoming-changes 事件
+fn sync_git_async(app_handle: tauri::AppHandle, config: Config) {
+    // 仅本地模式或未配置远程时不同步
+    if !config.sync_enabled() {
+        return;
+    }
 
-/// 在后台线程中异步执行 git 同步（不阻塞 UI）
-fn sync_git_async(local_path: String) {
     std::thread::spawn(move || {
-        let mut pull_cmd = std::process::Command::new("git");
-        pull_cmd.args(["pull"]).current_dir(&local_path);
+        // 持有 git_manager 锁，与前台 git 命令、后台重试和仓库维护串行执行
+        let state: tauri::State<AppState> = app_handle.state();
+        let _git_manager = state.git_manager.lock().unwrap();
 
-        #[cfg(windows)]
-        pull_cmd.creation_flags(CREATE_NO_WINDOW);
-
-        let _ = pull_cmd.output();
-
-        let mut push_cmd = std::process::Command::new("git");
-        push_cmd.args(["push"]).current_dir(&local_path);
+        match GitManager::pull_at(&config) {
+            Ok(changes) if changes.commits > 0 => {
+                let _ = app_handle.emit_all("incoming-changes", changes);
+            }
+            Err(e) => {
+                // 远程历史被改写或无关：不推送，等待用户选择处理方式
+                if let Some(divergence) = e.downcast_ref::<Divergence>() {
+                    let _ = app_handle.emit_all("history-diverged", divergence.clone());
+                    return;
+                }
+            }
+            _ => {}
+        }
 
-        #[cfg(windows)]
-        push_cmd.creation_flags(CREATE_NO_WINDOW);
+        // 推送失败时记录到同步日志，由后台线程稍后重试
+        let result = GitManager::push_at(&config);
+        sync_queue::record_push_result(&config, &result);
 
-        let _ = push_cmd.output();
+        if result.is_ok() && config.enable_github_pages {
+            if let Err(e) = GitManager::publish_pages_at(&config) {
+                eprintln!("发布站点失败: {}", e);
+            }
+        }
     });
 }
 
 // 全局状态
 struct AppState {
+    /// 所有修改仓库的 git 操作（前台命令、后台同步与重试、仓库维护）都持有此锁串行执行
     git_manager: Mutex<Option<GitManager>>,
     file_manager: Mutex<FileManager>,
     config: Mutex<Option<Config>>,
+    /// 取消正在进行的克隆
+    clone_cancel: Arc<AtomicBool>,
 }
 
 #[tauri::command]
@@ -91,7 +127,7 @@ async fn write_file(
     state: State<'_, AppState>,
     filepath: String,
     content: String,
-) -> Result<String, String> {
+) -> Result<String, CommitError> {
     let file_manager = state.file_manager.lock().unwrap();
     let config = state.config.lock().unwrap();
     
@@ -101,13 +137,13 @@ async fn write_file(
         
         // 自动提交到 Git
         if let Some(git_mgr) = state.git_manager.lock().unwrap().as_ref() {
-            git_mgr.add_and_commit(&filepath, &format!("更新 {}", filepath))
-                .map_err(|e| e.to_string())?;
+            let message = git_mgr.commit_message(CommitAction::Update, &MessageVars::file(&filepath));
+            git_mgr.add_and_commit(&filepath, &message)?;
         }
         
         Ok("文件保存成功".to_string())
     } else {
-        Err("Git 未初始化".to_string())
+        Err("Git 未初始化".into())
     }
 }
 
@@ -128,55 +164,264 @@ async fn list_files(
 }
 
 #[tauri::command]
-async fn git_push(state: State<'_, AppState>) -> Result<String, String> {
+async fn git_push(state: State<'_, AppState>) -> Result<PushResult, String> {
     let git_manager = state.git_manager.lock().unwrap();
     
     if let Some(git_mgr) = git_manager.as_ref() {
-        git_mgr.push()
-            .map_err(|e| e.to_string())?;
-        Ok("推送成功".to_string())
+        if !git_mgr.config().sync_enabled() {
+            return Err(git_manager::LOCAL_ONLY_ERROR.to_string());
+        }
+        let result = git_mgr.push();
+        sync_queue::record_push_result(git_mgr.config(), &result);
+        result.map_err(|e| e.to_string())?;
+
+        // 推送已经成功，发布站点失败单独报告
+        let mut push_result = PushResult::default();
+        if git_mgr.config().enable_github_pages {
+            if let Err(e) = GitManager::publish_pages_at(git_mgr.config()) {
+                push_result.pages_error = Some(e.to_string());
+            }
+        }
+        Ok(push_result)
     } else {
         Err("Git 未初始化".to_string())
     }
 }
 
 #[tauri::command]
-async fn git_pull(state: State<'_, AppState>) -> Result<String, String> {
+async fn git_pull(state: State<'_, AppState>) -> Result<IncomingChanges, PullError> {
     let git_manager = state.git_manager.lock().unwrap();
     
     if let Some(git_mgr) = git_manager.as_ref() {
-        git_mgr.pull()
+        Ok(git_mgr.pull()?)
+    } else {
+        Err(PullError {
+            message: "Git 未初始化".to_string(),
+            divergence: None,
+        })
+    }
+}
+
+/// 诊断仓库状态（残留锁文件、游离 HEAD、未完成的合并等），verify_objects 时检查对象完整性
+#[tauri::command]
+async fn check_repo_health(
+    state: State<'_, AppState>,
+    verify_objects: Option<bool>,
+) -> Result<Vec<HealthIssue>, String> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        git_mgr.check_health(verify_objects.unwrap_or(false))
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Git 未初始化".to_string())
+    }
+}
+
+/// 修复可以安全处理的仓库问题
+#[tauri::command]
+async fn repair_repo(state: State<'_, AppState>) -> Result<RepairReport, String> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        git_mgr.repair()
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Git 未初始化".to_string())
+    }
+}
+
+/// 仓库体积统计（对象数、包大小、最大文件、各年份附件大小）
+#[tauri::command]
+async fn repo_stats(state: State<'_, AppState>) -> Result<RepoStats, String> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        let mut stats = git_mgr.repo_stats()
+            .map_err(|e| e.to_string())?;
+        stats.last_maintenance = maintenance::load_last_run(git_mgr.local_path());
+        Ok(stats)
+    } else {
+        Err("Git 未初始化".to_string())
+    }
+}
+
+/// 立即执行仓库维护（gc/repack 并清除过期的不可达对象）
+#[tauri::command]
+async fn run_maintenance(state: State<'_, AppState>) -> Result<MaintenanceReport, String> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        maintenance::run(git_mgr)
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Git 未初始化".to_string())
+    }
+}
+
+/// 按设备统计提交活动，days 限定最近天数
+#[tauri::command]
+async fn device_activity(
+    state: State<'_, AppState>,
+    days: Option<u32>,
+) -> Result<Vec<DeviceActivity>, String> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        git_mgr.device_activity(days)
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Git 未初始化".to_string())
+    }
+}
+
+/// 为仅本地使用的仓库连接远程：合并远程已有历史（或推送到空仓库）并设置上游，之后退出仅本地模式
+#[tauri::command]
+async fn attach_remote(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+    remote_url: String,
+    token: Option<String>,
+    git_provider: Option<String>,
+) -> Result<AttachResult, String> {
+    let current = state.config.lock().unwrap().clone().ok_or("未配置本地目录")?;
+    let config = Config {
+        remote_url: Some(remote_url),
+        local_only: false,
+        token: token.or(current.token.clone()),
+        git_provider: git_provider.unwrap_or(current.git_provider.clone()),
+        ..current
+    };
+
+    let mut git_manager = state.git_manager.lock().unwrap();
+    let previous_origin = GitManager::origin_url_at(&config.local_path);
+    let git_mgr = GitManager::new(config.clone())
+        .map_err(|e| e.to_string())?;
+
+    // 失败时恢复原来的 origin，保持仅本地模式
+    let result = match git_mgr.init().and_then(|_| GitManager::attach_remote_at(&config)) {
+        Ok(result) => result,
+        Err(e) => {
+            if let Err(restore_err) = GitManager::restore_origin_at(&config.local_path, previous_origin.as_deref()) {
+                eprintln!("恢复远程设置失败: {}", restore_err);
+            }
+            return Err(e.to_string());
+        }
+    };
+
+    let config = write_config(&app_handle, config)?;
+    *git_manager = Some(GitManager::new(config.clone()).map_err(|e| e.to_string())?);
+    drop(git_manager);
+    *state.config.lock().unwrap() = Some(config);
+
+    Ok(result)
+}
+
+/// 检查本地与远程历史的关系（远程被强制推送、仓库被重建等）
+#[tauri::command]
+async fn check_divergence(state: State<'_, AppState>) -> Result<Divergence, String> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        GitManager::check_divergence_at(git_mgr.config())
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Git 未初始化".to_string())
+    }
+}
+
+/// 处理被改写或无关的远程历史：force_push, take_remote, merge_unrelated（操作前创建备份引用）
+#[tauri::command]
+async fn resolve_divergence(
+    state: State<'_, AppState>,
+    choice: String,
+) -> Result<DivergenceResolution, String> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        GitManager::resolve_divergence_at(git_mgr.config(), &choice)
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Git 未初始化".to_string())
+    }
+}
+
+/// 获取同步状态（未推送提交数和待推送日志）
+#[tauri::command]
+async fn get_sync_status(state: State<'_, AppState>) -> Result<SyncStatus, String> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        let unpushed_commits = git_mgr.unpushed_count()
             .map_err(|e| e.to_string())?;
-        Ok("拉取成功".to_string())
+        Ok(SyncStatus {
+            unpushed_commits,
+            journal: sync_queue::load_journal(git_mgr.local_path()),
+        })
     } else {
         Err("Git 未初始化".to_string())
     }
 }
 
+/// 获取各远程仓库（主仓库和备份镜像）的最近推送状态
+#[tauri::command]
+async fn get_remote_status(state: State<'_, AppState>) -> Result<Vec<RemoteStatus>, String> {
+    let config = state.config.lock().unwrap();
+
+    if let Some(cfg) = config.as_ref() {
+        Ok(sync_queue::load_remote_status(&cfg.local_path))
+    } else {
+        Err("未配置本地目录".to_string())
+    }
+}
+
 #[tauri::command]
 async fn save_config(
     state: State<'_, AppState>,
     config: Config,
     app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
+    let config = write_config(&app_handle, config)?;
+
+    // 更新内存中的配置（含凭据明文和引用）
+    *state.config.lock().unwrap() = Some(config);
+
+    // 注意：.desktop_data/config.json 不再提交到 git（本地客户端数据不同步）
+
+    Ok("配置保存成功".to_string())
+}
+
+/// 仅本地模式下停用托盘的"同步数据"菜单
+fn update_tray_sync_item(app_handle: &tauri::AppHandle, config: &Config) {
+    let _ = app_handle
+        .tray_handle()
+        .get_item("sync")
+        .set_enabled(config.sync_enabled());
+}
+
+/// 将配置写入数据目录并更新指针，返回含凭据明文和引用的配置
+fn write_config(app_handle: &tauri::AppHandle, config: Config) -> Result<Config, String> {
     // 1. 在本地数据目录创建 .desktop_data 目录
     let desktop_data_dir = Path::new(&config.local_path).join(".desktop_data");
     std::fs::create_dir_all(&desktop_data_dir)
         .map_err(|e| format!("创建 .desktop_data 目录失败: {}", e))?;
 
-    // 2. 保存配置到 .desktop_data/config.json
+    let app_data_dir = app_handle
+        .path_resolver()
+        .app_data_dir()
+        .ok_or("无法获取应用数据目录")?;
+
+    // 2. 保存配置到 .desktop_data/config.json（凭据加密保存，配置中只保留引用）
+    let sealed = secrets::seal_config(&SecretStore::new(&app_data_dir), &config)
+        .map_err(|e| format!("保存凭据失败: {}", e))?;
     let config_path = desktop_data_dir.join("config.json");
-    let config_str = serde_json::to_string_pretty(&config)
+    let config_str = serde_json::to_string_pretty(&sealed)
         .map_err(|e| e.to_string())?;
     std::fs::write(&config_path, &config_str)
         .map_err(|e| format!("保存配置失败: {}", e))?;
 
     // 3. 保存数据目录指针到 Tauri 应用数据目录
-    let pointer_path = app_handle
-        .path_resolver()
-        .app_data_dir()
-        .ok_or("无法获取应用数据目录")?
-        .join("pointer.json");
+    let pointer_path = app_data_dir.join("pointer.json");
     std::fs::create_dir_all(pointer_path.parent().unwrap())
         .map_err(|e| e.to_string())?;
 
@@ -188,12 +433,13 @@ async fn save_config(
     std::fs::write(&pointer_path, pointer_str)
         .map_err(|e| format!("保存指针失败: {}", e))?;
 
-    // 4. 更新内存中的配置
-    *state.config.lock().unwrap() = Some(config.clone());
-
-    // 注意：.desktop_data/config.json 不再提交到 git（本地客户端数据不同步）
+    update_tray_sync_item(app_handle, &config);
 
-    Ok("配置保存成功".to_string())
+    Ok(Config {
+        token_ref: sealed.token_ref,
+        ssh_passphrase_ref: sealed.ssh_passphrase_ref,
+        ..config
+    })
 }
 
 #[tauri::command]
@@ -208,6 +454,7 @@ async fn load_config(
 
     let pointer_path = app_data_dir.join("pointer.json");
     let legacy_config_path = app_data_dir.join("config.json");
+    let secret_store = SecretStore::new(&app_data_dir);
 
     // 1. 尝试从指针加载
     if pointer_path.exists() {
@@ -223,7 +470,10 @@ async fn load_config(
                 .map_err(|e| e.to_string())?;
             let config: Config = serde_json::from_str(&config_str)
                 .map_err(|e| e.to_string())?;
+            let config = migrate_plaintext_secrets(&secret_store, &config_path, config);
+            let config = secrets::unseal_config(&secret_store, &config);
 
+            update_tray_sync_item(&app_handle, &config);
             *state.config.lock().unwrap() = Some(config.clone());
             return Ok(Some(config));
         }
@@ -236,7 +486,11 @@ async fn load_config(
         let config: Config = serde_json::from_str(&config_str)
             .map_err(|e| e.to_string())?;
 
-        // 迁移到新位置
+        // 迁移到新位置（同时加密明文凭据）
+        let config_str = secrets::seal_config(&secret_store, &config)
+            .ok()
+            .and_then(|sealed| serde_json::to_string_pretty(&sealed).ok())
+            .unwrap_or(config_str);
         let desktop_data_dir = Path::new(&config.local_path).join(".desktop_data");
         if std::fs::create_dir_all(&desktop_data_dir).is_ok() {
             let new_config_path = desktop_data_dir.join("config.json");
@@ -253,6 +507,7 @@ async fn load_config(
             }
         }
 
+        update_tray_sync_item(&app_handle, &config);
         *state.config.lock().unwrap() = Some(config.clone());
         return Ok(Some(config));
     }
@@ -260,23 +515,261 @@ async fn load_config(
     Ok(None)
 }
 
+/// 将旧版本写入的明文凭据迁移到安全存储并重写配置文件
+/// 迁移失败时保留原配置，下次加载再试
+fn migrate_plaintext_secrets(store: &SecretStore, config_path: &Path, config: Config) -> Config {
+    if !secrets::has_plaintext_secrets(&config) {
+        return config;
+    }
+
+    let sealed = match secrets::seal_config(store, &config) {
+        Ok(sealed) => sealed,
+        Err(e) => {
+            eprintln!("迁移明文凭据失败: {}", e);
+            return config;
+        }
+    };
+
+    match serde_json::to_string_pretty(&sealed) {
+        Ok(content) if std::fs::write(config_path, &content).is_ok() => sealed,
+        _ => config,
+    }
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn clone_repo(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
     url: String,
     path: String,
     token: Option<String>,
+    git_provider: Option<String>,
+    ssh_key_path: Option<String>,
+    ssh_passphrase: Option<String>,
+    depth: Option<u32>,
+) -> Result<String, String> {
+    let ssh_settings = SshSettings {
+        key_path: ssh_key_path.filter(|s| !s.is_empty()),
+        passphrase: ssh_passphrase.filter(|s| !s.is_empty()),
+        known_hosts_path: None,
+    };
+    let cancel = state.clone_cancel.clone();
+
+    // 在阻塞线程中克隆，进度通过 clone-progress 事件发送，期间可调用 cancel_clone
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut on_progress = |progress: &CloneProgress| {
+            let _ = app_handle.emit_all("clone-progress", progress);
+        };
+
+        // 返回实际克隆的路径
+        GitManager::clone_repo(
+            &url,
+            &path,
+            token.as_deref(),
+            git_provider.as_deref(),
+            &ssh_settings,
+            depth,
+            &cancel,
+            &mut on_progress,
+        )
+        .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 取消正在进行的克隆（未完成的目录会被删除）
+#[tauri::command]
+async fn cancel_clone(state: State<'_, AppState>) -> Result<(), String> {
+    state.clone_cancel.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// 使用给定的签名设置签署一段测试内容，失败时返回结构化错误
+#[tauri::command]
+async fn test_commit_signing(
+    state: State<'_, AppState>,
+    signing: SigningConfig,
+) -> Result<String, signing::SigningError> {
+    let config = state.config.lock().unwrap().clone().unwrap_or_default();
+    signing::sign(&signing, "todo-desktop signing test\n", git_manager::local_timeout(&config))?;
+    Ok("签名测试成功".to_string())
+}
+
+/// 指定风格的内置提交信息模板（操作名 → 模板）
+#[tauri::command]
+async fn get_commit_templates(style: String) -> Result<HashMap<String, String>, String> {
+    Ok(commit_message::builtin_templates(&style))
+}
+
+/// 校验访问令牌格式是否符合平台规范
+#[tauri::command]
+async fn validate_token(git_provider: String, token: String) -> Result<(), String> {
+    provider::provider_for(&git_provider, None)
+        .validate_token(token.trim())
+        .map_err(|e| e.to_string())
+}
+
+/// 使用已保存的令牌在 Git 平台上创建仓库，并设为当前数据目录的远程仓库
+#[tauri::command]
+async fn create_remote_repo(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+    name: String,
+    private: bool,
+) -> Result<String, String> {
+    let config = state.config.lock().unwrap().clone()
+        .ok_or("未配置本地目录")?;
+    let token = config.token.clone()
+        .filter(|t| !t.is_empty())
+        .ok_or("请先配置访问令牌")?;
+
+    let git_provider = provider::provider_for(&config.git_provider, config.provider_base_url.as_deref());
+    let remote_url = remote_api::create_repo(git_provider.as_ref(), &token, &name, private)
+        .map_err(|e| e.to_string())?;
+
+    let config = write_config(&app_handle, Config {
+        remote_url: Some(remote_url.clone()),
+        ..config
+    })?;
+
+    let git_manager = GitManager::new(config.clone())
+        .map_err(|e| e.to_string())?;
+    git_manager.init()
+        .map_err(|e| e.to_string())?;
+
+    *state.git_manager.lock().unwrap() = Some(git_manager);
+    *state.config.lock().unwrap() = Some(config);
+
+    Ok(remote_url)
+}
+
+/// 获取远程仓库的网页地址（自建平台需提供根地址）
+#[tauri::command]
+async fn get_repo_web_url(
+    git_provider: String,
+    remote_url: String,
+    base_url: Option<String>,
+) -> Result<String, String> {
+    let remote = provider::parse_remote(&remote_url)
+        .ok_or("无法解析远程仓库地址")?;
+    Ok(provider::provider_for(&git_provider, base_url.as_deref()).web_url(&remote))
+}
+
+/// 测试远程仓库连接（url 为空时测试当前配置的主仓库）
+#[tauri::command]
+async fn test_remote_connection(
+    config: Config,
+    url: Option<String>,
+) -> Result<String, String> {
+    GitManager::test_connection(&config, url.as_deref())
+        .map_err(|e| e.to_string())?;
+    Ok("连接成功".to_string())
+}
+
+/// 生成 SSH 密钥对，返回公钥
+#[tauri::command]
+async fn generate_ssh_key(
+    key_path: String,
+    passphrase: Option<String>,
+    comment: Option<String>,
 ) -> Result<String, String> {
-    // 返回实际克隆的路径
-    GitManager::clone_repo(&url, &path, token.as_deref())
+    ssh::generate_key(
+        &key_path,
+        passphrase.as_deref().unwrap_or_default(),
+        comment.as_deref().unwrap_or("todo-desktop"),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// 获取 SSH 主机公钥指纹，供用户首次连接时核对
+#[tauri::command]
+async fn scan_ssh_host_keys(url: String) -> Result<Vec<SshHostKey>, String> {
+    ssh::scan_host_keys(&url)
         .map_err(|e| e.to_string())
 }
 
+/// 将用户已核对指纹的 SSH 主机公钥加入 known_hosts
 #[tauri::command]
-async fn detect_git_config(path: String) -> Result<Option<GitInfo>, String> {
-    GitManager::detect_config(&path)
+async fn add_ssh_known_host(
+    url: String,
+    known_hosts_path: Option<String>,
+    fingerprints: Vec<String>,
+) -> Result<Vec<String>, String> {
+    ssh::add_known_host(&url, known_hosts_path.as_deref().filter(|s| !s.is_empty()), &fingerprints)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn detect_git_config(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<Option<GitInfo>, String> {
+    let custom_providers = state.config.lock().unwrap()
+        .as_ref()
+        .map(|cfg| cfg.custom_providers.clone())
+        .unwrap_or_default();
+
+    GitManager::detect_config(&path, &custom_providers)
         .map_err(|e| e.to_string())
 }
 
+/// 扫描现有 Markdown 文件夹，报告符合日文件结构和可以转换的文件
+#[tauri::command]
+async fn scan_markdown_folder(path: String) -> Result<FolderScan, String> {
+    adopt::scan_folder(&path)
+        .map_err(|e| e.to_string())
+}
+
+/// 将现有 Markdown 文件夹作为 Todo 仓库：可选地转换近似文件名，然后作为一次导入提交
+#[tauri::command]
+async fn adopt_folder(
+    state: State<'_, AppState>,
+    config: Config,
+    convert: bool,
+) -> Result<AdoptResult, String> {
+    let scan = adopt::scan_folder(&config.local_path)
+        .map_err(|e| e.to_string())?;
+
+    let renamed = if convert {
+        adopt::convert_near_matches(&config.local_path, &scan)
+            .map_err(|e| format!("转换文件失败: {}", e))?
+    } else {
+        vec![]
+    };
+
+    let git_manager = GitManager::new(config.clone())
+        .map_err(|e| e.to_string())?;
+
+    let message = git_manager.commit_message(
+        CommitAction::Import,
+        &MessageVars {
+            count: Some(scan.matched.len() + renamed.len()),
+            renamed: Some(renamed.len()),
+            ..Default::default()
+        },
+    );
+    let paths = adopt::import_paths(&scan, &renamed);
+    let removed: Vec<String> = renamed.iter().map(|r| r.from.clone()).collect();
+    let imported = git_manager.import_all(&paths, &removed, &message)
+        .map_err(|e| e.to_string())?;
+
+    // 设置远程仓库等（仓库已有导入提交，不会再创建初始提交）
+    git_manager.init()
+        .map_err(|e| e.to_string())?;
+
+    *state.git_manager.lock().unwrap() = Some(git_manager);
+    *state.config.lock().unwrap() = Some(config);
+
+    Ok(AdoptResult {
+        imported_files: imported.as_ref().map(|(_, count)| *count).unwrap_or(0),
+        day_files: scan.matched.len() + renamed.len(),
+        renamed,
+        commit: imported.map(|(id, _)| id),
+    })
+}
+
 #[tauri::command]
 async fn is_git_repo(path: String) -> Result<bool, String> {
     Ok(GitManager::is_git_repo(&path))
@@ -341,19 +834,109 @@ async fn resolve_conflict(
 #[tauri::command]
 async fn complete_merge(
     state: State<'_, AppState>,
-    message: String,
-) -> Result<String, String> {
+    message: Option<String>,
+) -> Result<String, CommitError> {
     let git_manager = state.git_manager.lock().unwrap();
 
     if let Some(git_mgr) = git_manager.as_ref() {
-        git_mgr.complete_merge(&message)
-            .map_err(|e| e.to_string())?;
+        // 未指定提交信息时使用模板
+        let message = message
+            .filter(|m| !m.trim().is_empty())
+            .unwrap_or_else(|| git_mgr.commit_message(CommitAction::ResolveConflicts, &MessageVars::default()));
+        git_mgr.complete_merge(&message)?;
         Ok("合并完成".to_string())
+    } else {
+        Err("Git 未初始化".into())
+    }
+}
+
+/// 获取文件的提交历史
+#[tauri::command]
+async fn get_file_history(
+    state: State<'_, AppState>,
+    filepath: String,
+    limit: Option<usize>,
+) -> Result<Vec<FileCommit>, String> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        git_mgr.file_history(&filepath, limit)
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Git 未初始化".to_string())
+    }
+}
+
+/// 获取文件在指定提交时的内容
+#[tauri::command]
+async fn get_file_at_commit(
+    state: State<'_, AppState>,
+    filepath: String,
+    commit: String,
+) -> Result<String, String> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        git_mgr.get_file_at_ref(&commit, &filepath)
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Git 未初始化".to_string())
+    }
+}
+
+/// 比较文件两个版本的任务差异（to 为空时与当前内容比较）
+#[tauri::command]
+async fn diff_file_versions(
+    state: State<'_, AppState>,
+    filepath: String,
+    from: String,
+    to: Option<String>,
+) -> Result<TaskDiff, String> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        git_mgr.diff_file_versions(&filepath, &from, to.as_deref())
+            .map_err(|e| e.to_string())
     } else {
         Err("Git 未初始化".to_string())
     }
 }
 
+/// 将文件恢复到指定版本
+#[tauri::command]
+async fn restore_file(
+    state: State<'_, AppState>,
+    filepath: String,
+    commit: String,
+) -> Result<String, CommitError> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        git_mgr.restore_file(&filepath, &commit)?;
+        Ok("文件已恢复".to_string())
+    } else {
+        Err("Git 未初始化".into())
+    }
+}
+
+/// 从指定版本找回单个已删除的任务
+#[tauri::command]
+async fn restore_task(
+    state: State<'_, AppState>,
+    filepath: String,
+    commit: String,
+    text: String,
+) -> Result<String, CommitError> {
+    let git_manager = state.git_manager.lock().unwrap();
+
+    if let Some(git_mgr) = git_manager.as_ref() {
+        git_mgr.restore_task(&filepath, &commit, &text)?;
+        Ok("任务已恢复".to_string())
+    } else {
+        Err("Git 未初始化".into())
+    }
+}
+
 #[tauri::command]
 async fn upload_attachment(
     state: State<'_, AppState>,
@@ -361,7 +944,7 @@ async fn upload_attachment(
     month: String,
     filename: String,
     data: Vec<u8>,
-) -> Result<String, String> {
+) -> Result<String, CommitError> {
     let file_manager = state.file_manager.lock().unwrap();
     let config = state.config.lock().unwrap();
 
@@ -379,13 +962,13 @@ async fn upload_attachment(
 
         let git_manager = state.git_manager.lock().unwrap();
         if let Some(git_mgr) = git_manager.as_ref() {
-            git_mgr.add_and_commit(&git_path, &format!("上传附件 {}", filename))
-                .map_err(|e| e.to_string())?;
+            let message = git_mgr.commit_message(CommitAction::UploadAttachment, &MessageVars::file(&filename));
+            git_mgr.add_and_commit(&git_path, &message)?;
         }
 
         Ok(relative_path)
     } else {
-        Err("未配置本地目录".to_string())
+        Err("未配置本地目录".into())
     }
 }
 
@@ -395,7 +978,7 @@ async fn delete_attachments(
     year: String,
     month: String,
     paths: Vec<String>,
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<String>, CommitError> {
     let file_manager = state.file_manager.lock().unwrap();
     let config = state.config.lock().unwrap();
 
@@ -406,21 +989,53 @@ async fn delete_attachments(
 
         // 如果有文件被删除，提交到 git
         if !deleted.is_empty() {
+            let local_path = cfg.local_path.clone();
+            let retention_days = cfg.trash_retention_days;
             drop(file_manager);
             drop(config);
 
+            let mut entries: Vec<TrashEntry> = vec![];
+            let mut commit_error = None;
             let git_manager = state.git_manager.lock().unwrap();
-            if let Some(git_mgr) = git_manager.as_ref() {
-                for path in &deleted {
-                    // 使用 remove_and_commit 从 git 中删除文件
-                    let _ = git_mgr.remove_and_commit(path, &format!("删除附件 {}", path));
+            let previous_commit = git_manager.as_ref().and_then(|git_mgr| git_mgr.head_commit_id().ok());
+            for path in &deleted {
+                // 使用 remove_and_commit 从 git 中删除文件，记录删除提交以便从回收站恢复
+                // 提交失败（如签名失败）后不再继续提交，文件仍记入回收站
+                let mut commit = None;
+                if let (Some(git_mgr), None) = (git_manager.as_ref(), &commit_error) {
+                    let message = git_mgr.commit_message(CommitAction::DeleteAttachment, &MessageVars::file(path));
+                    match git_mgr.remove_and_commit(path, &message) {
+                        Ok(()) => commit = git_mgr.head_commit_id().ok(),
+                        Err(e) => commit_error = Some(e),
+                    }
                 }
+
+                entries.push(TrashEntry {
+                    id: trash::generate_entry_id(path, ""),
+                    kind: "attachment".to_string(),
+                    filepath: path.clone(),
+                    source_date: None,
+                    text: None,
+                    section: None,
+                    lines: vec![],
+                    commit,
+                    previous_commit: previous_commit.clone(),
+                    deleted_at: trash::now(),
+                });
+            }
+            drop(git_manager);
+
+            if let Err(e) = trash::record(&local_path, entries, retention_days) {
+                eprintln!("记录回收站失败: {}", e);
+            }
+            if let Some(e) = commit_error {
+                return Err(e.into());
             }
         }
 
         Ok(deleted)
     } else {
-        Err("未配置本地目录".to_string())
+        Err("未配置本地目录".into())
     }
 }
 
@@ -929,14 +1544,14 @@ async fn delete_past_task(
     state: State<'_, AppState>,
     source_date: String,
     text: String,
-) -> Result<(), String> {
+) -> Result<(), CommitError> {
     let config = state.config.lock().unwrap();
 
     if let Some(cfg) = config.as_ref() {
         // 解析日期获取文件路径
         let parts: Vec<&str> = source_date.split('-').collect();
         if parts.len() != 3 {
-            return Err("日期格式错误".to_string());
+            return Err("日期格式错误".into());
         }
         let year = parts[0];
         let month = parts[1];
@@ -948,51 +1563,146 @@ async fn delete_past_task(
             .join(format!("{}.md", day));
 
         if !filepath.exists() {
-            return Err("源文件不存在".to_string());
+            return Err("源文件不存在".into());
         }
 
         // 读取文件内容
         let content = fs::read_to_string(&filepath)
             .map_err(|e| e.to_string())?;
 
-        // 移除匹配的任务行及其子内容
-        let mut new_lines: Vec<String> = vec![];
-        let mut skip_children = false;
-        let task_regex = Regex::new(&format!(r"^-\s*\[\s\]\s*{}$", regex::escape(&text))).unwrap();
-
-        for line in content.lines() {
-            if skip_children {
-                // 如果是缩进的行（子任务或子内容），继续跳过
-                if line.starts_with("  ") || line.trim().is_empty() {
-                    continue;
-                } else {
-                    skip_children = false;
-                }
-            }
-
-            // 检查是否是要删除的任务
-            if task_regex.is_match(line.trim_start_matches(' ')) && !line.starts_with("  ") {
-                skip_children = true;
-                continue;
-            }
-
-            new_lines.push(line.to_string());
-        }
+        // 只移除第一个匹配的任务及其子内容（与记入回收站的行完全一致），同名的其他任务保留
+        let task = task_parser::parse_tasks(&content)
+            .into_iter()
+            .find(|t| !t.completed && t.text == text)
+            .ok_or("源文件中找不到该任务")?;
 
         // 写回文件
-        let new_content = new_lines.join("\n");
-        fs::write(&filepath, &new_content)
+        fs::write(&filepath, task_parser::remove_task(&content, &task))
             .map_err(|e| e.to_string())?;
 
         // 提交到 git
+        let local_path = cfg.local_path.clone();
+        let retention_days = cfg.trash_retention_days;
         drop(config);
+        let git_path = format!("{}/{}/{}.md", year, month, day);
         let git_manager = state.git_manager.lock().unwrap();
+        let mut commit = None;
+        let mut commit_error = None;
+        let previous_commit = git_manager.as_ref().and_then(|git_mgr| git_mgr.head_commit_id().ok());
         if let Some(git_mgr) = git_manager.as_ref() {
-            let git_path = format!("{}/{}/{}.md", year, month, day);
-            let _ = git_mgr.add_and_commit(&git_path, &format!("删除往期任务: {}", text));
+            let message = git_mgr.commit_message(
+                CommitAction::DeleteTask,
+                &MessageVars { file: Some(&git_path), task: Some(&text), ..Default::default() },
+            );
+            // 提交失败（如签名失败）时任务已从文件中删除，仍记入回收站后返回错误
+            match git_mgr.add_and_commit(&git_path, &message) {
+                Ok(()) => commit = git_mgr.head_commit_id().ok(),
+                Err(e) => commit_error = Some(e),
+            }
+        }
+        drop(git_manager);
+
+        let entry = TrashEntry {
+            id: trash::generate_entry_id(&git_path, &text),
+            kind: "task".to_string(),
+            filepath: git_path,
+            source_date: Some(source_date),
+            text: Some(text),
+            section: Some(task.section),
+            lines: task.lines,
+            commit,
+            previous_commit,
+            deleted_at: trash::now(),
+        };
+        if let Err(e) = trash::record(&local_path, vec![entry], retention_days) {
+            eprintln!("记录回收站失败: {}", e);
         }
 
-        Ok(())
+        match commit_error {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
+    } else {
+        Err("未配置本地目录".into())
+    }
+}
+
+/// 列出回收站条目（同时清理过期条目）
+#[tauri::command]
+async fn list_trash(state: State<'_, AppState>) -> Result<Vec<TrashEntry>, String> {
+    let config = state.config.lock().unwrap();
+
+    if let Some(cfg) = config.as_ref() {
+        let mut data = trash::load_trash(&cfg.local_path);
+        if trash::purge_expired(&mut data, cfg.trash_retention_days) > 0 {
+            trash::save_trash(&cfg.local_path, &data)
+                .map_err(|e| e.to_string())?;
+        }
+
+        // 最近删除的在前
+        let mut entries = data.entries;
+        entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+        Ok(entries)
+    } else {
+        Err("未配置本地目录".to_string())
+    }
+}
+
+/// 从回收站恢复任务或附件
+#[tauri::command]
+async fn restore_from_trash(state: State<'_, AppState>, id: String) -> Result<String, CommitError> {
+    let local_path = state.config.lock().unwrap()
+        .as_ref()
+        .map(|cfg| cfg.local_path.clone())
+        .ok_or("未配置本地目录")?;
+
+    let mut data = trash::load_trash(&local_path);
+    let index = data.entries.iter()
+        .position(|e| e.id == id)
+        .ok_or("回收站中找不到该条目")?;
+    let entry = data.entries[index].clone();
+
+    let git_manager = state.git_manager.lock().unwrap();
+    let git_mgr = git_manager.as_ref().ok_or("Git 未初始化")?;
+
+    match entry.kind.as_str() {
+        "task" => {
+            let text = entry.text.as_deref().unwrap_or_default();
+            git_mgr.insert_task_and_commit(
+                &entry.filepath,
+                entry.section.as_deref().unwrap_or_default(),
+                &entry.lines,
+                text,
+            )?;
+        }
+        "attachment" => {
+            // 附件内容保存在删除前的 HEAD 中（旧条目只记录了删除提交，使用其父提交）
+            let source = match (&entry.previous_commit, &entry.commit) {
+                (Some(previous), _) => previous.clone(),
+                (None, Some(commit)) => format!("{}^", commit),
+                (None, None) => return Err("该附件没有对应的 Git 提交，无法恢复".into()),
+            };
+            git_mgr.restore_file(&entry.filepath, &source)?;
+        }
+        other => return Err(format!("未知的回收站条目类型: {}", other).into()),
+    }
+    drop(git_manager);
+
+    data.entries.remove(index);
+    trash::save_trash(&local_path, &data)
+        .map_err(|e| e.to_string())?;
+
+    Ok("已从回收站恢复".to_string())
+}
+
+/// 清空回收站
+#[tauri::command]
+async fn empty_trash(state: State<'_, AppState>) -> Result<(), String> {
+    let config = state.config.lock().unwrap();
+
+    if let Some(cfg) = config.as_ref() {
+        trash::save_trash(&cfg.local_path, &Default::default())
+            .map_err(|e| e.to_string())
     } else {
         Err("未配置本地目录".to_string())
     }
@@ -1064,9 +1774,9 @@ fn main() {
                     let state: tauri::State<AppState> = app.state();
                     let config = state.config.lock().unwrap();
                     if let Some(cfg) = config.as_ref() {
-                        let local_path = cfg.local_path.clone();
+                        let cfg = cfg.clone();
                         drop(config);
-                        sync_git_async(local_path);
+                        sync_git_async(app.clone(), cfg);
                     }
                 }
                 "quit" => {
@@ -1074,10 +1784,10 @@ fn main() {
                     let state: tauri::State<AppState> = app.state();
                     let config = state.config.lock().unwrap();
                     if let Some(cfg) = config.as_ref() {
-                        let local_path = cfg.local_path.clone();
+                        let cfg = cfg.clone();
                         drop(config);
                         // 启动异步同步（git 命令会在独立进程中执行）
-                        sync_git_async(local_path);
+                        sync_git_async(app.clone(), cfg);
                     }
                     app.exit(0);
                 }
@@ -1098,18 +1808,64 @@ fn main() {
                     let state: tauri::State<AppState> = app.state();
                     let config = state.config.lock().unwrap();
                     if let Some(cfg) = config.as_ref() {
-                        let local_path = cfg.local_path.clone();
+                        let cfg = cfg.clone();
                         drop(config);
-                        sync_git_async(local_path);
+                        sync_git_async(app.clone(), cfg);
                     }
                 }
                 _ => {}
             }
         })
+        .setup(|app| {
+            // 传递令牌和口令的 askpass 脚本放在应用私有数据目录
+            if let Some(dir) = app.path_resolver().app_data_dir() {
+                credentials::set_askpass_dir(dir.join("askpass"));
+            }
+
+            // 后台重试离线期间未能推送的提交；正在执行其他 git 操作时跳过本轮
+            let handle = app.handle();
+            sync_queue::spawn_retry_worker(move || {
+                let state: tauri::State<AppState> = handle.state();
+                let config = state.config.lock().unwrap().clone();
+                let Some(config) = config else {
+                    return;
+                };
+                let Ok(_git_manager) = state.git_manager.try_lock() else {
+                    return;
+                };
+                match sync_queue::retry_pending_push(&config) {
+                    Some(Ok(changes)) if changes.commits > 0 => {
+                        let _ = handle.emit_all("incoming-changes", changes);
+                    }
+                    Some(Err(e)) => {
+                        if let Some(divergence) = e.downcast_ref::<Divergence>() {
+                            let _ = handle.emit_all("history-diverged", divergence.clone());
+                        }
+                    }
+                    _ => {}
+                }
+            });
+
+            // 空闲时定期维护仓库；正在执行其他 git 操作或后台同步时跳过本轮
+            let handle = app.handle();
+            maintenance::spawn_maintenance_worker(move || {
+                let state: tauri::State<AppState> = handle.state();
+                let Ok(git_manager) = state.git_manager.try_lock() else {
+                    return;
+                };
+                if let Some(git_mgr) = git_manager.as_ref().filter(|g| maintenance::is_due(g)) {
+                    if let Err(e) = maintenance::run(git_mgr) {
+                        eprintln!("仓库维护失败: {}", e);
+                    }
+                }
+            });
+            Ok(())
+        })
         .manage(AppState {
             git_manager: Mutex::new(None),
             file_manager: Mutex::new(FileManager::new()),
             config: Mutex::new(None),
+            clone_cancel: Arc::new(AtomicBool::new(false)),
         })
         .invoke_handler(tauri::generate_handler![
             init_git,
@@ -1118,11 +1874,33 @@ fn main() {
             list_files,
             git_push,
             git_pull,
+            get_sync_status,
+            check_repo_health,
+            repair_repo,
+            repo_stats,
+            run_maintenance,
+            device_activity,
+            attach_remote,
+            check_divergence,
+            resolve_divergence,
+            get_remote_status,
             save_config,
             load_config,
             clone_repo,
+            cancel_clone,
+            validate_token,
+            test_commit_signing,
+            get_commit_templates,
+            create_remote_repo,
+            get_repo_web_url,
+            test_remote_connection,
+            generate_ssh_key,
+            scan_ssh_host_keys,
+            add_ssh_known_host,
             detect_git_config,
             is_git_repo,
+            scan_markdown_folder,
+            adopt_folder,
             upload_attachment,
             delete_attachments,
             has_conflicts,
@@ -1130,6 +1908,11 @@ fn main() {
             get_conflict_versions,
             resolve_conflict,
             complete_merge,
+            get_file_history,
+            get_file_at_commit,
+            diff_file_versions,
+            restore_file,
+            restore_task,
             load_stats,
             save_stats,
             recalculate_stats,
@@ -1138,6 +1921,9 @@ fn main() {
             save_past_uncompleted,
             scan_past_uncompleted,
             delete_past_task,
+            list_trash,
+            restore_from_trash,
+            empty_trash,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::config::MaintenanceReport;
+use crate::git_manager::GitManager;
+
+/// 最近一次提交距今超过该时长才视为空闲
+const IDLE_AFTER: Duration = Duration::from_secs(10 * 60);
+/// 后台检查间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// 获取维护记录文件路径
+pub fn get_record_path(local_path: &str) -> PathBuf {
+    Path::new(local_path).join(".desktop_data").join("maintenance.json")
+}
+
+/// 加载最近一次维护结果
+pub fn load_last_run(local_path: &str) -> Option<MaintenanceReport> {
+    let content = fs::read_to_string(get_record_path(local_path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_last_run(local_path: &str, report: &MaintenanceReport) -> Result<()> {
+    let path = get_record_path(local_path);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(report)?)?;
+
+    Ok(())
+}
+
+/// 执行维护并记录结果
+pub fn run(git_mgr: &GitManager) -> Result<MaintenanceReport> {
+    let report = git_mgr.run_maintenance()?;
+    if let Err(e) = save_last_run(git_mgr.local_path(), &report) {
+        eprintln!("保存维护记录失败: {}", e);
+    }
+    Ok(report)
+}
+
+/// 是否应在后台执行维护：已到维护间隔，且最近一段时间没有新提交
+pub fn is_due(git_mgr: &GitManager) -> bool {
+    let interval_days = git_mgr.config().maintenance_interval_days;
+    if interval_days == 0 {
+        return false;
+    }
+
+    let idle = git_mgr.since_last_commit().is_some_and(|d| d >= IDLE_AFTER);
+    if !idle {
+        return false;
+    }
+
+    match load_last_run(git_mgr.local_path()) {
+        Some(last) => DateTime::parse_from_rfc3339(&last.ran_at)
+            .map(|t| Local::now().signed_duration_since(t).num_days() >= interval_days as i64)
+            .unwrap_or(true),
+        None => true,
+    }
+}
+
+/// 启动后台维护线程，run_if_idle 在空闲且到期时执行维护
+pub fn spawn_maintenance_worker<F>(run_if_idle: F)
+where
+    F: Fn() + Send + 'static,
+{
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL_INTERVAL);
+        run_if_idle();
+    });
+}
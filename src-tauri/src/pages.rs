@@ -0,0 +1,369 @@
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use git2::{FileMode, Oid, Repository, Signature};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use crate::config::SigningConfig;
+use crate::signing;
+use crate::task_parser;
+
+/// 站点发布到的分支
+pub const PAGES_BRANCH: &str = "gh-pages";
+
+/// 一个日文件
+struct DayPage {
+    date: NaiveDate,
+    content: String,
+    total: usize,
+    completed: usize,
+}
+
+/// 从 HEAD 读取所有日文件（YYYY/MM/MM-DD.md），不访问工作区
+fn collect_days(repo: &Repository, exclude_notes: bool) -> Result<Vec<DayPage>> {
+    let day_regex = Regex::new(r"^(\d{4})/(\d{2})/(\d{2})-(\d{2})\.md$").unwrap();
+    let tree = repo.head()?.peel_to_tree()?;
+    let mut days = vec![];
+
+    tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+        let path = format!("{}{}", dir, entry.name().unwrap_or(""));
+        let Some(caps) = day_regex.captures(&path) else {
+            return git2::TreeWalkResult::Ok;
+        };
+        // 文件名中的月份必须与目录一致
+        if caps[2] != caps[3] {
+            return git2::TreeWalkResult::Ok;
+        }
+        let date = NaiveDate::from_ymd_opt(
+            caps[1].parse().unwrap_or(0),
+            caps[3].parse().unwrap_or(0),
+            caps[4].parse().unwrap_or(0),
+        );
+        let blob = entry.to_object(repo).ok().and_then(|o| o.into_blob().ok());
+
+        if let (Some(date), Some(blob)) = (date, blob) {
+            let mut content = String::from_utf8_lossy(blob.content()).to_string();
+            if exclude_notes {
+                content = strip_notes(&content);
+            }
+            let tasks = task_parser::parse_tasks(&content);
+            days.push(DayPage {
+                date,
+                total: tasks.len(),
+                completed: tasks.iter().filter(|t| t.completed).count(),
+                content,
+            });
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+
+    days.sort_by_key(|d| d.date);
+    Ok(days)
+}
+
+/// 移除 "## 笔记" 区域（到下一个二级标题为止）
+fn strip_notes(content: &str) -> String {
+    let mut result = vec![];
+    let mut in_notes = false;
+
+    for line in content.lines() {
+        if let Some(title) = line.strip_prefix("## ") {
+            in_notes = title.trim() == "笔记";
+        }
+        if !in_notes {
+            result.push(line);
+        }
+    }
+
+    let mut stripped = result.join("\n").trim_end().to_string();
+    stripped.push('\n');
+    stripped
+}
+
+/// 生成站点文件（相对路径 → 内容）
+pub fn build_site(repo: &Repository, exclude_notes: bool) -> Result<BTreeMap<String, Vec<u8>>> {
+    let days = collect_days(repo, exclude_notes)?;
+    let mut files = BTreeMap::new();
+
+    // 禁用 Jekyll，直接按静态文件发布
+    files.insert(".nojekyll".to_string(), vec![]);
+    files.insert("style.css".to_string(), STYLE.as_bytes().to_vec());
+    files.insert("index.html".to_string(), render_index(&days).into_bytes());
+    files.insert("stats.html".to_string(), render_stats(&days).into_bytes());
+
+    for day in &days {
+        files.insert(
+            format!("days/{}.html", day.date.format("%Y-%m-%d")),
+            render_day(day).into_bytes(),
+        );
+    }
+
+    Ok(files)
+}
+
+/// 将站点提交到 gh-pages 分支（只写对象库，不修改工作区和索引）
+/// 内容与上次发布相同时不提交，返回是否产生了新提交
+pub fn publish(
+    repo: &Repository,
+    files: &BTreeMap<String, Vec<u8>>,
+    signature: &Signature,
+    signing_config: &SigningConfig,
+    signing_timeout: Option<Duration>,
+    message: &str,
+) -> Result<bool> {
+    let tree_id = write_tree(repo, files)?;
+    let refname = format!("refs/heads/{}", PAGES_BRANCH);
+
+    let parent = repo
+        .find_reference(&refname)
+        .ok()
+        .and_then(|r| r.peel_to_commit().ok());
+    if parent.as_ref().is_some_and(|p| p.tree_id() == tree_id) {
+        return Ok(false);
+    }
+
+    let tree = repo.find_tree(tree_id)?;
+
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    signing::commit(repo, signing_config, signing_timeout, Some(&refname), signature, signature, message, &tree, &parents)?;
+    Ok(true)
+}
+
+/// 按目录层级写入树对象
+fn write_tree(repo: &Repository, files: &BTreeMap<String, Vec<u8>>) -> Result<Oid> {
+    let mut root = repo.treebuilder(None)?;
+    let mut subdirs: BTreeMap<&str, BTreeMap<String, Vec<u8>>> = BTreeMap::new();
+
+    for (path, content) in files {
+        match path.split_once('/') {
+            Some((dir, rest)) => {
+                subdirs.entry(dir).or_default().insert(rest.to_string(), content.clone());
+            }
+            None => {
+                let blob = repo.blob(content)?;
+                root.insert(path, blob, FileMode::Blob.into())?;
+            }
+        }
+    }
+
+    for (dir, entries) in subdirs {
+        let subtree = write_tree(repo, &entries)?;
+        root.insert(dir, subtree, FileMode::Tree.into())?;
+    }
+
+    Ok(root.write()?)
+}
+
+fn render_day(day: &DayPage) -> String {
+    use pulldown_cmark::{Event, Tag};
+
+    // 站点是公开的：原始 HTML 按文本转义输出，脚本类链接去掉地址
+    let parser = pulldown_cmark::Parser::new_ext(&day.content, pulldown_cmark::Options::ENABLE_TASKLISTS)
+        .map(|event| match event {
+            Event::Html(html) => Event::Text(html),
+            Event::Start(Tag::Link(kind, url, title)) if !is_safe_url(&url) => {
+                Event::Start(Tag::Link(kind, "".into(), title))
+            }
+            Event::Start(Tag::Image(kind, url, title)) if !is_safe_url(&url) => {
+                Event::Start(Tag::Image(kind, "".into(), title))
+            }
+            event => event,
+        });
+    let mut body = String::new();
+    pulldown_cmark::html::push_html(&mut body, parser);
+
+    let title = day.date.format("%Y-%m-%d").to_string();
+    page(
+        &title,
+        "../",
+        &format!(
+            "<p class=\"summary\">完成 {} / {}</p>\n<article>\n{}</article>\n",
+            day.completed, day.total, body
+        ),
+    )
+}
+
+/// 链接地址不能执行脚本（javascript:、vbscript:、data:）
+fn is_safe_url(url: &str) -> bool {
+    // 浏览器解析协议时会忽略空白和控制字符
+    let normalized: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    !["javascript:", "vbscript:", "data:"]
+        .iter()
+        .any(|scheme| normalized.starts_with(scheme))
+}
+
+fn render_index(days: &[DayPage]) -> String {
+    let by_date: BTreeMap<NaiveDate, &DayPage> = days.iter().map(|d| (d.date, d)).collect();
+    let mut months: BTreeMap<(i32, u32), ()> = BTreeMap::new();
+    for day in days {
+        months.insert((day.date.year(), day.date.month()), ());
+    }
+
+    let mut body = String::new();
+    if months.is_empty() {
+        body.push_str("<p>暂无内容</p>\n");
+    }
+
+    // 最近的月份在前
+    for &(year, month) in months.keys().rev() {
+        body.push_str(&format!("<section class=\"month\">\n<h2>{} 年 {} 月</h2>\n", year, month));
+        body.push_str("<table class=\"calendar\">\n<tr><th>一</th><th>二</th><th>三</th><th>四</th><th>五</th><th>六</th><th>日</th></tr>\n<tr>");
+
+        let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+        let offset = first.weekday().num_days_from_monday();
+        for _ in 0..offset {
+            body.push_str("<td></td>");
+        }
+
+        let mut date = first;
+        while date.month() == month {
+            if date.weekday().num_days_from_monday() == 0 && date != first {
+                body.push_str("</tr>\n<tr>");
+            }
+            match by_date.get(&date) {
+                Some(day) => body.push_str(&format!(
+                    "<td class=\"{}\"><a href=\"days/{}.html\" title=\"完成 {} / {}\">{}</a></td>",
+                    level_class(day),
+                    date.format("%Y-%m-%d"),
+                    day.completed,
+                    day.total,
+                    date.day()
+                )),
+                None => body.push_str(&format!("<td>{}</td>", date.day())),
+            }
+            date = date.succ_opt().unwrap();
+        }
+        body.push_str("</tr>\n</table>\n</section>\n");
+    }
+
+    page("Todo", "", &body)
+}
+
+fn render_stats(days: &[DayPage]) -> String {
+    let total: usize = days.iter().map(|d| d.total).sum();
+    let completed: usize = days.iter().map(|d| d.completed).sum();
+
+    let mut body = format!(
+        "<ul class=\"summary\">\n<li>记录天数：{}</li>\n<li>任务总数：{}</li>\n<li>已完成：{}</li>\n<li>完成率：{}</li>\n</ul>\n",
+        days.len(),
+        total,
+        completed,
+        percent(completed, total)
+    );
+
+    let mut months: BTreeMap<(i32, u32), (usize, usize, usize)> = BTreeMap::new();
+    for day in days {
+        let entry = months.entry((day.date.year(), day.date.month())).or_default();
+        entry.0 += 1;
+        entry.1 += day.total;
+        entry.2 += day.completed;
+    }
+
+    body.push_str("<table class=\"stats\">\n<tr><th>月份</th><th>天数</th><th>任务</th><th>已完成</th><th>完成率</th></tr>\n");
+    for ((year, month), (count, total, completed)) in months.iter().rev() {
+        body.push_str(&format!(
+            "<tr><td>{}-{:02}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            year,
+            month,
+            count,
+            total,
+            completed,
+            percent(*completed, *total)
+        ));
+    }
+    body.push_str("</table>\n");
+
+    page("统计", "", &body)
+}
+
+fn percent(completed: usize, total: usize) -> String {
+    if total == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.0}%", completed as f64 * 100.0 / total as f64)
+    }
+}
+
+/// 日历格子按完成度着色
+fn level_class(day: &DayPage) -> &'static str {
+    if day.total == 0 {
+        "level-0"
+    } else if day.completed == day.total {
+        "level-3"
+    } else if day.completed * 2 >= day.total {
+        "level-2"
+    } else {
+        "level-1"
+    }
+}
+
+fn page(title: &str, root: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n<link rel=\"stylesheet\" href=\"{root}style.css\">\n</head>\n<body>\n\
+         <nav><a href=\"{root}index.html\">日历</a> · <a href=\"{root}stats.html\">统计</a></nav>\n\
+         <h1>{title}</h1>\n{body}</body>\n</html>\n",
+        title = escape(title),
+        root = root,
+        body = body
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+const STYLE: &str = "body { max-width: 860px; margin: 2em auto; padding: 0 1em; font-family: -apple-system, \"Segoe UI\", \"PingFang SC\", \"Microsoft YaHei\", sans-serif; color: #24292f; }
+nav { margin-bottom: 1em; }
+a { color: #0969da; text-decoration: none; }
+.month { display: inline-block; vertical-align: top; margin: 0 1.5em 1.5em 0; }
+.calendar td, .calendar th { width: 2.2em; height: 2em; text-align: center; }
+.calendar td a { display: block; border-radius: 4px; }
+.level-0 a { background: #eaeef2; }
+.level-1 a { background: #9be9a8; }
+.level-2 a { background: #40c463; }
+.level-3 a { background: #216e39; color: #fff; }
+.stats { border-collapse: collapse; }
+.stats td, .stats th { border: 1px solid #d0d7de; padding: 0.3em 0.8em; text-align: right; }
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(content: &str) -> DayPage {
+        DayPage {
+            date: NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+            content: content.to_string(),
+            total: 0,
+            completed: 0,
+        }
+    }
+
+    #[test]
+    fn render_day_escapes_raw_html() {
+        let html = render_day(&day(
+            "# 标题\n\n<script>alert(1)</script>\n\n- [x] 完成 <img src=x onerror=alert(2)>\n",
+        ));
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("<img src=x"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(html.contains("<h1>标题</h1>"));
+        assert!(html.contains("checkbox"));
+    }
+
+    #[test]
+    fn render_day_drops_script_links() {
+        let html = render_day(&day(
+            "[a](javascript:alert(1)) [b]( JaVaScRiPt:alert(2)) ![c](data:text/html,x) [d](https://example.com)",
+        ));
+        assert!(!html.to_lowercase().contains("javascript:"));
+        assert!(!html.contains("data:text/html"));
+        assert!(html.contains("href=\"https://example.com\""));
+    }
+}
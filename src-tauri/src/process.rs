@@ -0,0 +1,103 @@
+use std::io::{Read, Write};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::time::{Duration, Instant};
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// 执行外部命令并收集输出，input 写入标准输入（为 None 时标准输入为空）
+/// 超过 timeout 时终止进程及其启动的子进程（ssh、pinentry 等），返回 Ok(None)
+pub fn output_with_timeout(
+    cmd: &mut Command,
+    input: Option<&[u8]>,
+    timeout: Option<Duration>,
+) -> std::io::Result<Option<Output>> {
+    cmd.stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    // 独立进程组，超时时可以一并终止子进程
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(cmd, 0);
+
+    let mut child = cmd.spawn()?;
+
+    // 在后台读写管道，避免管道写满导致进程阻塞
+    let stdin = child
+        .stdin
+        .take()
+        .zip(input.map(|input| input.to_vec()))
+        .map(|(mut pipe, input)| {
+            std::thread::spawn(move || {
+                let _ = pipe.write_all(&input);
+            })
+        });
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let status = match timeout {
+        Some(timeout) => wait_with_timeout(&mut child, timeout)?,
+        None => Some(child.wait()?),
+    };
+
+    let Some(status) = status else {
+        kill_process_tree(&mut child);
+        return Ok(None);
+    };
+
+    if let Some(stdin) = stdin {
+        let _ = stdin.join();
+    }
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }))
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+/// 等待进程结束，超时返回 None
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// 终止进程及其启动的子进程
+fn kill_process_tree(child: &mut Child) {
+    // 进程组号即子进程的 pid（见 process_group(0)）
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &child.id().to_string()])
+            .creation_flags(CREATE_NO_WINDOW)
+            .status();
+    }
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
@@ -0,0 +1,181 @@
+use anyhow::{Result, anyhow};
+use std::time::Duration;
+use crate::credentials;
+use crate::provider::GitProvider;
+
+/// 请求超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 通过平台 REST API 在当前用户下创建仓库，返回 HTTPS 克隆地址
+pub fn create_repo(provider: &dyn GitProvider, token: &str, name: &str, private: bool) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("仓库名不能为空"));
+    }
+
+    let (url, body) = provider.create_repo_request(name, private, token);
+
+    let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+    let mut request = agent
+        .post(&url)
+        .set("Accept", "application/json")
+        .set("User-Agent", "todo-desktop");
+    if let Some((header, value)) = provider.auth_header(token) {
+        request = request.set(&header, &value);
+    }
+
+    let response = match request.send_json(body) {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, response)) => {
+            let message = response.into_string().unwrap_or_default();
+            let reason = match status {
+                401 | 403 => "令牌无效或没有创建仓库的权限",
+                409 | 422 => "仓库已存在或名称不合法",
+                _ => "创建仓库失败",
+            };
+            return Err(anyhow!(
+                "{} ({}): {}",
+                reason,
+                status,
+                credentials::redact_secret(message.trim(), token)
+            ));
+        }
+        Err(e) => {
+            return Err(anyhow!(
+                "无法连接平台 API: {}",
+                credentials::redact_secret(&e.to_string(), token)
+            ));
+        }
+    };
+
+    let json: serde_json::Value = response.into_json()?;
+    provider
+        .clone_url_from_response(&json)
+        .ok_or_else(|| anyhow!("平台返回的数据中没有仓库地址"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::provider_with_api_base;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    const TOKEN: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    /// 本地模拟 HTTP 服务器：接受一个请求并返回指定响应，线程返回收到的请求
+    fn mock_server(status: u16, body: &str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}/api", listener.local_addr().unwrap());
+        let body = body.to_string();
+
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut payload = vec![0; content_length];
+            reader.read_exact(&mut payload).unwrap();
+            request.push_str(&String::from_utf8_lossy(&payload));
+
+            let response = format!(
+                "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            request
+        });
+
+        (base, handle)
+    }
+
+    fn create(id: &str, status: u16, body: &str) -> (Result<String>, String) {
+        let (base, server) = mock_server(status, body);
+        let provider = provider_with_api_base(id, None, Some(&base));
+        let result = create_repo(provider.as_ref(), TOKEN, "todo", true);
+        (result, server.join().unwrap())
+    }
+
+    #[test]
+    fn github_create_and_errors() {
+        let (result, request) = create("github", 201, r#"{"clone_url":"https://github.com/me/todo.git"}"#);
+        assert_eq!(result.unwrap(), "https://github.com/me/todo.git");
+        assert!(request.starts_with("POST /api/user/repos "));
+        assert!(request.contains(&format!("Bearer {}", TOKEN)));
+        assert!(request.contains(r#""private":true"#));
+
+        let (result, _) = create("github", 401, r#"{"message":"Bad credentials"}"#);
+        let error = result.unwrap_err().to_string();
+        assert!(error.starts_with("令牌无效或没有创建仓库的权限 (401)"), "{}", error);
+    }
+
+    #[test]
+    fn gitlab_create_and_errors() {
+        let (result, request) = create("gitlab", 201, r#"{"http_url_to_repo":"https://gitlab.com/me/todo.git"}"#);
+        assert_eq!(result.unwrap(), "https://gitlab.com/me/todo.git");
+        assert!(request.starts_with("POST /api/projects "));
+        assert!(request.to_lowercase().contains(&format!("private-token: {}", TOKEN)));
+        assert!(request.contains(r#""visibility":"private""#));
+
+        let (result, _) = create("gitlab", 400, r#"{"message":"bad request"}"#);
+        assert!(result.unwrap_err().to_string().starts_with("创建仓库失败 (400)"));
+    }
+
+    #[test]
+    fn gitee_create_and_errors() {
+        let (result, request) = create("gitee", 201, r#"{"full_name":"me/todo"}"#);
+        assert_eq!(result.unwrap(), "https://gitee.com/me/todo.git");
+        assert!(request.starts_with("POST /api/user/repos "));
+        assert!(!request.to_lowercase().contains("authorization:"));
+        assert!(request.contains(&format!(r#""access_token":"{}""#, TOKEN)));
+
+        // 错误信息中回显的令牌需要被移除
+        let (result, _) = create("gitee", 422, &format!(r#"{{"message":"exists","token":"{}"}}"#, TOKEN));
+        let error = result.unwrap_err().to_string();
+        assert!(error.starts_with("仓库已存在或名称不合法 (422)"), "{}", error);
+        assert!(!error.contains(TOKEN));
+    }
+
+    #[test]
+    fn gitea_create_and_errors() {
+        let (result, request) = create("gitea", 201, r#"{"clone_url":"https://git.home.lan/me/todo.git"}"#);
+        assert_eq!(result.unwrap(), "https://git.home.lan/me/todo.git");
+        assert!(request.starts_with("POST /api/user/repos "));
+        assert!(request.contains(&format!("token {}", TOKEN)));
+
+        let (result, _) = create("forgejo", 409, "{}");
+        assert!(result.unwrap_err().to_string().starts_with("仓库已存在或名称不合法 (409)"));
+
+        let (result, _) = create("forgejo", 201, "{}");
+        assert_eq!(result.unwrap_err().to_string(), "平台返回的数据中没有仓库地址");
+    }
+
+    #[test]
+    fn unreachable_api() {
+        let base = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}/api", listener.local_addr().unwrap())
+        };
+        let provider = provider_with_api_base("github", None, Some(&base));
+        let error = create_repo(provider.as_ref(), TOKEN, "todo", true).unwrap_err().to_string();
+        assert!(error.starts_with("无法连接平台 API"), "{}", error);
+        assert!(create_repo(provider.as_ref(), TOKEN, "  ", true).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::config::Config;
+
+/// 系统钥匙串中的服务名
+const KEYRING_SERVICE: &str = "todo-desktop";
+/// 可选：用户指定的口令（用于派生加密文件的密钥）
+const PASSPHRASE_ENV: &str = "TODO_DESKTOP_SECRET_PASSPHRASE";
+
+const KEYRING_PREFIX: &str = "keyring:";
+const FILE_PREFIX: &str = "file:";
+
+/// 密钥文件：保存派生密钥所需的盐和（未指定口令时）随机生成的口令
+///
+/// 未设置 TODO_DESKTOP_SECRET_PASSPHRASE 时口令以明文保存在 secrets.json 旁边（仅当前用户可读），
+/// 加密只能防止单独复制 secrets.json 造成泄露，不能防御可以读取应用数据目录的程序
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyFile {
+    salt: String,
+    passphrase: Option<String>,
+}
+
+/// 秘密存储：优先使用系统钥匙串，不可用时使用应用数据目录中的加密文件
+pub struct SecretStore {
+    app_data_dir: PathBuf,
+}
+
+impl SecretStore {
+    pub fn new(app_data_dir: &Path) -> Self {
+        Self {
+            app_data_dir: app_data_dir.to_path_buf(),
+        }
+    }
+
+    /// 保存秘密，返回写入 Config 的引用
+    pub fn store(&self, id: &str, secret: &str) -> Result<String> {
+        if store_in_keyring(id, secret).is_ok() {
+            return Ok(format!("{}{}", KEYRING_PREFIX, id));
+        }
+
+        let mut entries = self.load_file_entries()?;
+        entries.insert(id.to_string(), self.encrypt(secret)?);
+        self.save_file_entries(&entries)?;
+
+        Ok(format!("{}{}", FILE_PREFIX, id))
+    }
+
+    /// 根据引用读取秘密
+    pub fn load(&self, reference: &str) -> Result<String> {
+        if let Some(id) = reference.strip_prefix(KEYRING_PREFIX) {
+            let entry = keyring::Entry::new(KEYRING_SERVICE, id)?;
+            return Ok(entry.get_password()?);
+        }
+
+        if let Some(id) = reference.strip_prefix(FILE_PREFIX) {
+            let entries = self.load_file_entries()?;
+            let encrypted = entries.get(id).ok_or_else(|| anyhow!("找不到已保存的凭据: {}", id))?;
+            return self.decrypt(encrypted);
+        }
+
+        Err(anyhow!("无效的凭据引用: {}", reference))
+    }
+
+    /// 删除引用对应的秘密
+    pub fn delete(&self, reference: &str) -> Result<()> {
+        if let Some(id) = reference.strip_prefix(KEYRING_PREFIX) {
+            let entry = keyring::Entry::new(KEYRING_SERVICE, id)?;
+            let _ = entry.delete_password();
+        } else if let Some(id) = reference.strip_prefix(FILE_PREFIX) {
+            let mut entries = self.load_file_entries()?;
+            if entries.remove(id).is_some() {
+                self.save_file_entries(&entries)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn secrets_path(&self) -> PathBuf {
+        self.app_data_dir.join("secrets.json")
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.app_data_dir.join("secret.key")
+    }
+
+    fn load_file_entries(&self) -> Result<HashMap<String, String>> {
+        let path = self.secrets_path();
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+        let content = fs::read_to_string(&path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn save_file_entries(&self, entries: &HashMap<String, String>) -> Result<()> {
+        fs::create_dir_all(&self.app_data_dir)?;
+        write_private(&self.secrets_path(), &serde_json::to_string_pretty(entries)?)
+    }
+
+    /// 由口令和盐派生 AES-256 密钥（Argon2id）
+    fn derive_key(&self) -> Result<Key<Aes256Gcm>> {
+        let key_path = self.key_path();
+
+        let key_file: KeyFile = if key_path.exists() {
+            serde_json::from_str(&fs::read_to_string(&key_path)?)?
+        } else {
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+
+            // 未指定口令时生成随机口令保存在密钥文件中
+            let passphrase = if std::env::var(PASSPHRASE_ENV).is_ok() {
+                None
+            } else {
+                let mut random = [0u8; 32];
+                OsRng.fill_bytes(&mut random);
+                Some(BASE64.encode(random))
+            };
+
+            let key_file = KeyFile {
+                salt: BASE64.encode(salt),
+                passphrase,
+            };
+            fs::create_dir_all(&self.app_data_dir)?;
+            write_private(&key_path, &serde_json::to_string_pretty(&key_file)?)?;
+            key_file
+        };
+
+        let passphrase = match &key_file.passphrase {
+            Some(p) => p.clone(),
+            None => std::env::var(PASSPHRASE_ENV)
+                .map_err(|_| anyhow!("需要设置环境变量 {} 以解密凭据", PASSPHRASE_ENV))?,
+        };
+        let salt = BASE64.decode(&key_file.salt)?;
+
+        let mut key = [0u8; 32];
+        argon2::Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| anyhow!("派生密钥失败: {}", e))?;
+
+        Ok(key.into())
+    }
+
+    fn encrypt(&self, secret: &str) -> Result<String> {
+        let cipher = Aes256Gcm::new(&self.derive_key()?);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, secret.as_bytes())
+            .map_err(|_| anyhow!("加密凭据失败"))?;
+
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        Ok(BASE64.encode(data))
+    }
+
+    fn decrypt(&self, encrypted: &str) -> Result<String> {
+        let data = BASE64.decode(encrypted)?;
+        if data.len() < 12 {
+            return Err(anyhow!("凭据数据已损坏"));
+        }
+
+        let (nonce, ciphertext) = data.split_at(12);
+        let cipher = Aes256Gcm::new(&self.derive_key()?);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("解密凭据失败（口令或密钥文件不匹配）"))?;
+
+        Ok(String::from_utf8(plaintext)?)
+    }
+}
+
+/// 写入钥匙串并读回校验（部分平台没有可用的钥匙串服务）
+fn store_in_keyring(id: &str, secret: &str) -> Result<()> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, id)?;
+    entry.set_password(secret)?;
+    if entry.get_password()? != secret {
+        return Err(anyhow!("钥匙串校验失败"));
+    }
+    Ok(())
+}
+
+/// 仅当前用户可读写的文件
+fn write_private(path: &Path, content: &str) -> Result<()> {
+    fs::write(path, content)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+
+    Ok(())
+}
+
+/// 同一台机器可能有多个数据目录，按目录区分凭据（SHA-256，不随编译器版本变化）
+fn secret_id(config: &Config, name: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(config.local_path.as_bytes());
+    let hash: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}", name, hash)
+}
+
+/// 将配置中的明文秘密写入安全存储，返回只含引用的配置（用于写盘）
+pub fn seal_config(store: &SecretStore, config: &Config) -> Result<Config> {
+    let mut sealed = config.clone();
+
+    seal_field(store, config, "token", &mut sealed.token, &mut sealed.token_ref)?;
+    seal_field(
+        store,
+        config,
+        "ssh-passphrase",
+        &mut sealed.ssh_passphrase,
+        &mut sealed.ssh_passphrase_ref,
+    )?;
+
+    Ok(sealed)
+}
+
+fn seal_field(
+    store: &SecretStore,
+    config: &Config,
+    name: &str,
+    value: &mut Option<String>,
+    reference: &mut Option<String>,
+) -> Result<()> {
+    match value.take() {
+        Some(secret) if !secret.is_empty() => {
+            let new_reference = store.store(&secret_id(config, name), &secret)?;
+            // 旧版本生成的 id 不同，保存成功后删除旧条目
+            if let Some(old) = reference.take().filter(|old| *old != new_reference) {
+                if let Err(e) = store.delete(&old) {
+                    eprintln!("删除旧凭据失败: {}", e);
+                }
+            }
+            *reference = Some(new_reference);
+        }
+        Some(_) => {
+            // 界面提交空字符串表示清除凭据
+            if let Some(old) = reference.take() {
+                store.delete(&old)?;
+            }
+        }
+        // 没有明文（未修改，或读取安全存储失败）时保留原引用
+        None => {}
+    }
+    Ok(())
+}
+
+/// 解析配置中的引用，返回含明文秘密的配置（只保存在内存中）
+pub fn unseal_config(store: &SecretStore, config: &Config) -> Config {
+    let mut unsealed = config.clone();
+
+    if let Some(reference) = &config.token_ref {
+        match store.load(reference) {
+            Ok(secret) => unsealed.token = Some(secret),
+            Err(e) => eprintln!("读取访问令牌失败: {}", e),
+        }
+    }
+    if let Some(reference) = &config.ssh_passphrase_ref {
+        match store.load(reference) {
+            Ok(secret) => unsealed.ssh_passphrase = Some(secret),
+            Err(e) => eprintln!("读取 SSH 口令失败: {}", e),
+        }
+    }
+
+    unsealed
+}
+
+/// 配置文件中是否还有明文秘密（旧版本写入）
+pub fn has_plaintext_secrets(config: &Config) -> bool {
+    config.token.as_ref().is_some_and(|t| !t.is_empty())
+        || config.ssh_passphrase.as_ref().is_some_and(|p| !p.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_id_is_stable() {
+        let config = Config {
+            local_path: "/home/me/todo".to_string(),
+            ..Default::default()
+        };
+        // 引用写入配置文件，id 必须在不同版本间保持一致
+        assert_eq!(secret_id(&config, "token"), "token-9bf245ddac2a3660");
+    }
+
+    #[test]
+    fn missing_plaintext_keeps_reference() {
+        let dir = std::env::temp_dir().join(format!("todo-secrets-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = SecretStore::new(&dir);
+        let mut entries = HashMap::new();
+        entries.insert("token-x".to_string(), "encrypted".to_string());
+        store.save_file_entries(&entries).unwrap();
+        let config = Config::default();
+
+        // 读取失败时明文为空，保存配置不能删除已保存的凭据
+        let mut value = None;
+        let mut reference = Some("file:token-x".to_string());
+        seal_field(&store, &config, "token", &mut value, &mut reference).unwrap();
+        assert_eq!(reference.as_deref(), Some("file:token-x"));
+        assert!(store.load_file_entries().unwrap().contains_key("token-x"));
+
+        // 空字符串表示用户清除了凭据
+        let mut value = Some(String::new());
+        seal_field(&store, &config, "token", &mut value, &mut reference).unwrap();
+        assert_eq!(reference, None);
+        assert!(store.load_file_entries().unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
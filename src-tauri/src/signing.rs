@@ -0,0 +1,203 @@
+use anyhow::Result;
+use git2::{Commit, Oid, Repository, Signature, Tree};
+use serde::Serialize;
+use std::process::Command;
+use std::time::Duration;
+use crate::config::SigningConfig;
+use crate::process;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// 签名失败（不会退回为未签名提交）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningError {
+    /// 错误类型: program_not_found, key_missing, signing_failed, timeout
+    pub kind: String,
+    /// 使用的签名程序
+    pub program: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SigningError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "提交签名失败（{}）: {}", self.program, self.message)
+    }
+}
+
+impl std::error::Error for SigningError {}
+
+/// 创建提交的命令的错误：签名失败时保留结构化的 SigningError，其余为错误信息
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum CommitError {
+    Signing(SigningError),
+    Message(String),
+}
+
+impl From<anyhow::Error> for CommitError {
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<SigningError>() {
+            Ok(signing) => Self::Signing(signing),
+            Err(error) => Self::Message(error.to_string()),
+        }
+    }
+}
+
+impl From<String> for CommitError {
+    fn from(message: String) -> Self {
+        Self::Message(message)
+    }
+}
+
+impl From<&str> for CommitError {
+    fn from(message: &str) -> Self {
+        Self::Message(message.to_string())
+    }
+}
+
+impl SigningError {
+    fn new(kind: &str, program: &str, message: impl Into<String>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            program: program.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+fn program(signing: &SigningConfig) -> String {
+    match signing.program.as_deref().filter(|p| !p.is_empty()) {
+        Some(program) => program.to_string(),
+        None if signing.is_ssh() => "ssh-keygen".to_string(),
+        None => "gpg".to_string(),
+    }
+}
+
+/// 对提交内容签名，返回 ASCII 格式的签名
+/// 超过 timeout 时（如 pinentry 一直等待输入口令）终止签名程序
+pub fn sign(signing: &SigningConfig, content: &str, timeout: Option<Duration>) -> std::result::Result<String, SigningError> {
+    let program = program(signing);
+    let key = signing.key.as_deref().filter(|k| !k.is_empty());
+
+    let mut cmd = Command::new(&program);
+    if signing.is_ssh() {
+        let key = key.ok_or_else(|| SigningError::new("key_missing", &program, "SSH 签名需要指定密钥文件"))?;
+        if !std::path::Path::new(key).exists() {
+            return Err(SigningError::new("key_missing", &program, format!("密钥文件 {} 不存在", key)));
+        }
+        // 不指定文件时 ssh-keygen 从标准输入读取内容并把签名写到标准输出
+        cmd.args(["-Y", "sign", "-n", "git", "-f", key]);
+    } else {
+        cmd.args(["--status-fd=2", "-bsa"]);
+        if let Some(key) = key {
+            cmd.args(["-u", key]);
+        }
+    }
+
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let output = process::output_with_timeout(&mut cmd, Some(content.as_bytes()), timeout)
+        .map_err(|e| SigningError::new("program_not_found", &program, format!("无法执行签名程序: {}", e)))?
+        .ok_or_else(|| {
+            let secs = timeout.map(|t| t.as_secs()).unwrap_or_default();
+            SigningError::new(
+                "timeout",
+                &program,
+                format!("签名程序 {} 秒内未完成（可能在等待输入口令），已终止", secs),
+            )
+        })?;
+    let signature = String::from_utf8_lossy(&output.stdout).to_string();
+
+    if !output.status.success() || signature.trim().is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // gpg 的状态行对用户没有意义
+        let message = stderr
+            .lines()
+            .filter(|l| !l.starts_with("[GNUPG:]"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let kind = if message.contains("No secret key") || message.contains("No such file") {
+            "key_missing"
+        } else {
+            "signing_failed"
+        };
+        return Err(SigningError::new(kind, &program, message.trim()));
+    }
+
+    Ok(signature)
+}
+
+/// 创建提交：启用签名时写入签名后再更新引用
+#[allow(clippy::too_many_arguments)]
+pub fn commit(
+    repo: &Repository,
+    signing: &SigningConfig,
+    timeout: Option<Duration>,
+    update_ref: Option<&str>,
+    author: &Signature,
+    committer: &Signature,
+    message: &str,
+    tree: &Tree,
+    parents: &[&Commit],
+) -> Result<Oid> {
+    if !signing.enabled {
+        return Ok(repo.commit(update_ref, author, committer, message, tree, parents)?);
+    }
+
+    let buffer = repo.commit_create_buffer(author, committer, message, tree, parents)?;
+    let content = buffer.as_str().unwrap_or_default().to_string();
+    let signature = sign(signing, &content, timeout)?;
+    let oid = repo.commit_signed(&content, &signature, None)?;
+
+    if let Some(refname) = update_ref {
+        // HEAD 指向分支（可能尚未创建）时更新分支本身
+        let target = match repo.find_reference(refname) {
+            Ok(reference) => reference
+                .symbolic_target()
+                .map(|t| t.to_string())
+                .unwrap_or_else(|| refname.to_string()),
+            Err(_) => refname.to_string(),
+        };
+        let summary = message.lines().next().unwrap_or_default();
+        repo.reference(&target, oid, true, &format!("commit: {}", summary))?;
+    }
+
+    Ok(oid)
+}
+
+/// 将签名设置写入仓库配置，使系统 git 创建的提交（如拉取时的合并提交）同样签名
+pub fn configure_repo(repo: &Repository, signing: &SigningConfig) -> Result<()> {
+    let mut config = repo.config()?;
+
+    if !signing.enabled {
+        let _ = config.remove("commit.gpgsign");
+        return Ok(());
+    }
+
+    config.set_bool("commit.gpgsign", true)?;
+    config.set_str("gpg.format", if signing.is_ssh() { "ssh" } else { "openpgp" })?;
+    match signing.key.as_deref().filter(|k| !k.is_empty()) {
+        Some(key) => config.set_str("user.signingkey", key)?,
+        None => {
+            let _ = config.remove("user.signingkey");
+        }
+    }
+    // 只保留当前格式的签名程序，清空设置或切换格式后移除之前写入的值
+    let active = if signing.is_ssh() { "gpg.ssh.program" } else { "gpg.program" };
+    for key in ["gpg.program", "gpg.ssh.program"] {
+        match signing.program.as_deref().filter(|p| !p.is_empty()) {
+            Some(program) if key == active => config.set_str(key, program)?,
+            _ => {
+                let _ = config.remove(key);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,298 @@
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+use crate::config::{Config, SshHostKey};
+use crate::credentials;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// SSH 连接设置
+#[derive(Debug, Clone, Default)]
+pub struct SshSettings {
+    /// 私钥路径（为空时使用 ssh 默认密钥）
+    pub key_path: Option<String>,
+    /// 私钥口令
+    pub passphrase: Option<String>,
+    /// known_hosts 文件路径（为空时使用 ~/.ssh/known_hosts）
+    pub known_hosts_path: Option<String>,
+}
+
+impl SshSettings {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            key_path: config.ssh_key_path.clone().filter(|s| !s.is_empty()),
+            passphrase: config.ssh_passphrase.clone().filter(|s| !s.is_empty()),
+            known_hosts_path: config.ssh_known_hosts_path.clone().filter(|s| !s.is_empty()),
+        }
+    }
+
+    /// 生成 git 使用的环境变量（GIT_SSH_COMMAND 以及口令的 askpass）
+    /// 未配置任何 SSH 选项时沿用系统 ssh 设置
+    pub fn git_env(&self) -> Result<Vec<(String, String)>> {
+        let mut env = Vec::new();
+
+        if self.key_path.is_some() || self.known_hosts_path.is_some() {
+            env.push(("GIT_SSH_COMMAND".to_string(), self.ssh_command()));
+        }
+
+        if let Some(passphrase) = &self.passphrase {
+            env.extend(credentials::ssh_passphrase_env(passphrase)?);
+        }
+
+        Ok(env)
+    }
+
+    /// 构建 ssh 命令：指定私钥，并严格校验主机密钥
+    fn ssh_command(&self) -> String {
+        let mut cmd = String::from("ssh -o StrictHostKeyChecking=yes");
+
+        if let Some(key) = &self.key_path {
+            cmd.push_str(&format!(" -i {} -o IdentitiesOnly=yes", quote_path(key)));
+        }
+        if let Some(known_hosts) = &self.known_hosts_path {
+            cmd.push_str(&format!(" -o UserKnownHostsFile={}", quote_path(known_hosts)));
+        }
+
+        cmd
+    }
+}
+
+/// 判断是否为 SSH 地址（git@host:path 或 ssh://）
+pub fn is_ssh_url(url: &str) -> bool {
+    if url.starts_with("ssh://") || url.starts_with("git+ssh://") {
+        return true;
+    }
+    if url.contains("://") {
+        return false;
+    }
+
+    // scp 风格：[user@]host:path，排除 Windows 盘符（C:\...）
+    match url.find(':') {
+        Some(pos) => pos > 1 && !url[..pos].contains('/'),
+        None => false,
+    }
+}
+
+/// 从 SSH 地址解析主机名和端口
+pub fn parse_ssh_host(url: &str) -> Option<(String, Option<u16>)> {
+    if !is_ssh_url(url) {
+        return None;
+    }
+
+    let rest = url
+        .strip_prefix("ssh://")
+        .or_else(|| url.strip_prefix("git+ssh://"));
+
+    let authority = match rest {
+        Some(rest) => rest.split('/').next()?,
+        None => url.split(':').next()?,
+    };
+    let host_port = authority.rsplit('@').next()?;
+
+    match host_port.rsplit_once(':') {
+        Some((host, port)) if rest.is_some() => Some((host.to_string(), port.parse().ok())),
+        _ => Some((host_port.to_string(), None)),
+    }
+}
+
+/// 检查主机公钥是否在指定的 known_hosts 文件中（只比较未哈希的条目，
+/// add_known_host 写入的条目均为未哈希格式）
+pub fn is_known_host(known_hosts_path: &str, host: &str, key: &[u8]) -> bool {
+    use base64::Engine;
+
+    let content = std::fs::read_to_string(known_hosts_path).unwrap_or_default();
+    let bracketed = format!("[{}]:", host);
+
+    content.lines().any(|line| {
+        let mut fields = line.split_whitespace();
+        let (Some(hosts), Some(_key_type), Some(encoded)) = (fields.next(), fields.next(), fields.next()) else {
+            return false;
+        };
+        let host_matches = hosts
+            .split(',')
+            .any(|h| h == host || h.starts_with(&bracketed));
+
+        host_matches
+            && base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .is_ok_and(|decoded| decoded == key)
+    })
+}
+
+/// 生成 ed25519 密钥对，返回公钥内容（用于粘贴到 Git 平台）
+pub fn generate_key(key_path: &str, passphrase: &str, comment: &str) -> Result<String> {
+    let path = Path::new(key_path);
+    if path.exists() {
+        return Err(anyhow!("密钥文件 {} 已存在", key_path));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut cmd = std::process::Command::new("ssh-keygen");
+    cmd.args(["-q", "-t", "ed25519", "-f", key_path, "-C", comment])
+        .stdin(std::process::Stdio::null());
+
+    // 口令通过 askpass 提供，不出现在命令行参数中（其他用户可从进程列表读取）
+    if passphrase.is_empty() {
+        cmd.args(["-N", ""]);
+    } else {
+        cmd.envs(credentials::ssh_passphrase_env(passphrase)?);
+    }
+
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow!("无法执行 ssh-keygen: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("{}", stderr.trim()));
+    }
+
+    let public_key = std::fs::read_to_string(format!("{}.pub", key_path))?;
+    Ok(public_key.trim().to_string())
+}
+
+/// 获取远程主机的公钥及其指纹，供用户核对（不写入 known_hosts）
+pub fn scan_host_keys(url: &str) -> Result<Vec<SshHostKey>> {
+    let (host, port) = parse_ssh_host(url).ok_or_else(|| anyhow!("无法从地址解析主机: {}", url))?;
+
+    let mut cmd = std::process::Command::new("ssh-keyscan");
+    if let Some(port) = port {
+        cmd.args(["-p", &port.to_string()]);
+    }
+    cmd.arg(&host);
+
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow!("无法执行 ssh-keyscan: {}", e))?;
+
+    let keys: Vec<SshHostKey> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        .filter_map(parse_host_key)
+        .collect();
+
+    if keys.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("无法获取主机 {} 的公钥: {}", host, stderr.trim()));
+    }
+
+    Ok(keys)
+}
+
+/// 解析 known_hosts 格式的条目（主机 类型 公钥），计算 SHA256 指纹
+fn parse_host_key(entry: &str) -> Option<SshHostKey> {
+    use base64::Engine;
+    use sha2::{Digest, Sha256};
+
+    let mut fields = entry.split_whitespace();
+    let (host, key_type, encoded) = (fields.next()?, fields.next()?, fields.next()?);
+    let key = base64::engine::general_purpose::STANDARD.decode(encoded).ok()?;
+    let digest = Sha256::digest(&key);
+
+    Some(SshHostKey {
+        host: host.to_string(),
+        key_type: key_type.to_string(),
+        fingerprint: format!("SHA256:{}", base64::engine::general_purpose::STANDARD_NO_PAD.encode(digest)),
+        entry: entry.to_string(),
+    })
+}
+
+/// 将用户已核对指纹的主机公钥写入 known_hosts，返回写入的条目
+/// 重新获取公钥，只写入指纹在 fingerprints 中的条目；都不匹配时（公钥在确认后发生变化）拒绝写入
+pub fn add_known_host(url: &str, known_hosts_path: Option<&str>, fingerprints: &[String]) -> Result<Vec<String>> {
+    let entries: Vec<String> = scan_host_keys(url)?
+        .into_iter()
+        .filter(|key| fingerprints.contains(&key.fingerprint))
+        .map(|key| key.entry)
+        .collect();
+
+    if entries.is_empty() {
+        return Err(anyhow!("主机公钥与确认的指纹不一致，可能存在中间人攻击，已拒绝写入 known_hosts"));
+    }
+
+    let path = match known_hosts_path {
+        Some(p) => PathBuf::from(p),
+        None => default_known_hosts()?,
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut content = std::fs::read_to_string(&path).unwrap_or_default();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    for entry in &entries {
+        if !content.lines().any(|l| l == entry) {
+            content.push_str(entry);
+            content.push('\n');
+        }
+    }
+    std::fs::write(&path, content)?;
+
+    Ok(entries)
+}
+
+fn default_known_hosts() -> Result<PathBuf> {
+    Ok(ssh_dir()?.join("known_hosts"))
+}
+
+/// ssh 默认尝试的私钥（存在的）
+pub fn default_identity_files() -> Vec<PathBuf> {
+    let Ok(dir) = ssh_dir() else {
+        return vec![];
+    };
+    ["id_ed25519", "id_ecdsa", "id_rsa"]
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+fn ssh_dir() -> Result<PathBuf> {
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .ok_or_else(|| anyhow!("无法确定用户主目录"))?;
+    Ok(PathBuf::from(home).join(".ssh"))
+}
+
+/// 为 GIT_SSH_COMMAND 引用路径（git 通过 sh 执行，统一使用正斜杠）
+fn quote_path(path: &str) -> String {
+    format!("\"{}\"", path.replace('\\', "/").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_key_fingerprint_matches_ssh_keygen() {
+        let entry = "example.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAILD3EwAGs+fq6wsTEuU1wBxxIpLX1EvlqV1zsgC4M/+J";
+        let key = parse_host_key(entry).unwrap();
+
+        assert_eq!(key.host, "example.com");
+        assert_eq!(key.key_type, "ssh-ed25519");
+        // ssh-keygen -l -f known_hosts 的输出
+        assert_eq!(key.fingerprint, "SHA256:HkV3fns8ANnLWECZgGk/VWe6UC3Cqm0eGRm6mmsXWio");
+        assert!(parse_host_key("example.com ssh-ed25519 !!!").is_none());
+    }
+
+    #[test]
+    fn parses_ssh_hosts() {
+        assert_eq!(parse_ssh_host("git@github.com:me/todo.git"), Some(("github.com".into(), None)));
+        assert_eq!(parse_ssh_host("ssh://git@host.lan:2222/me/todo.git"), Some(("host.lan".into(), Some(2222))));
+        assert_eq!(parse_ssh_host("https://github.com/me/todo.git"), None);
+        assert!(!is_ssh_url(r"C:\notes\todo"));
+    }
+}
@@ -0,0 +1,322 @@
+use anyhow::Result;
+use chrono::Local;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::config::{Config, IncomingChanges, RemoteStatus, SyncJournal};
+use crate::divergence::Divergence;
+use crate::git_manager::GitManager;
+
+/// 首次重试间隔（秒）
+const BASE_RETRY_SECS: i64 = 30;
+/// 最大重试间隔（秒）
+const MAX_RETRY_SECS: i64 = 30 * 60;
+/// 后台检查间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// 获取同步日志文件路径
+pub fn get_journal_path(local_path: &str) -> PathBuf {
+    Path::new(local_path).join(".desktop_data").join("sync_queue.json")
+}
+
+/// 加载同步日志（文件不存在或损坏时返回空日志）
+pub fn load_journal(local_path: &str) -> SyncJournal {
+    let path = get_journal_path(local_path);
+
+    if path.exists() {
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        serde_json::from_str(&content).unwrap_or_default()
+    } else {
+        SyncJournal::default()
+    }
+}
+
+/// 保存同步日志
+pub fn save_journal(local_path: &str, journal: &SyncJournal) -> Result<()> {
+    let path = get_journal_path(local_path);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let content = serde_json::to_string_pretty(journal)?;
+    fs::write(&path, content)?;
+
+    Ok(())
+}
+
+/// 计算第 attempts 次失败后的重试间隔（指数退避）
+pub fn backoff_secs(attempts: u32) -> i64 {
+    let exp = attempts.saturating_sub(1).min(16);
+    (BASE_RETRY_SECS << exp).min(MAX_RETRY_SECS)
+}
+
+/// 记录新的本地提交：离线期间的提交也会被后台推送
+pub fn mark_pending(local_path: &str) {
+    let mut journal = load_journal(local_path);
+    if journal.pending {
+        return;
+    }
+
+    journal.pending = true;
+    journal.next_retry_at = Some(Local::now().timestamp() + BASE_RETRY_SECS);
+
+    if let Err(e) = save_journal(local_path, &journal) {
+        eprintln!("保存同步日志失败: {}", e);
+    }
+}
+
+/// 推送是否被远程拒绝（而不是网络等临时故障）
+pub fn is_rejection(error: &anyhow::Error) -> bool {
+    if error.downcast_ref::<Divergence>().is_some() {
+        return true;
+    }
+    let message = error.to_string();
+    ["[rejected]", "[remote rejected]", "non-fast-forward", "fetch first"]
+        .iter()
+        .any(|marker| message.contains(marker))
+}
+
+/// 记录推送被拒绝：停止后台重试，等待用户手动同步
+pub fn mark_blocked(local_path: &str, error: &str) {
+    let mut journal = load_journal(local_path);
+
+    journal.pending = true;
+    journal.blocked = true;
+    journal.last_error = Some(error.to_string());
+    journal.last_attempt = Some(Local::now().to_rfc3339());
+    journal.next_retry_at = None;
+
+    if let Err(e) = save_journal(local_path, &journal) {
+        eprintln!("保存同步日志失败: {}", e);
+    }
+}
+
+/// 记录推送失败，安排下次重试
+pub fn mark_failed(local_path: &str, error: &str) {
+    let mut journal = load_journal(local_path);
+    let now = Local::now();
+
+    journal.pending = true;
+    journal.attempts += 1;
+    journal.last_error = Some(error.to_string());
+    journal.last_attempt = Some(now.to_rfc3339());
+    journal.next_retry_at = Some(now.timestamp() + backoff_secs(journal.attempts));
+
+    if let Err(e) = save_journal(local_path, &journal) {
+        eprintln!("保存同步日志失败: {}", e);
+    }
+}
+
+/// 记录推送成功，清除待推送状态
+pub fn mark_synced(local_path: &str) {
+    let journal = load_journal(local_path);
+    if !journal.pending {
+        return;
+    }
+
+    let journal = SyncJournal {
+        last_attempt: Some(Local::now().to_rfc3339()),
+        ..Default::default()
+    };
+
+    if let Err(e) = save_journal(local_path, &journal) {
+        eprintln!("保存同步日志失败: {}", e);
+    }
+}
+
+/// 记录同步失败：被拒绝时停止重试，否则按退避间隔重试
+fn record_failure(local_path: &str, error: &anyhow::Error) {
+    if is_rejection(error) {
+        mark_blocked(local_path, &error.to_string());
+    } else {
+        mark_failed(local_path, &error.to_string());
+    }
+}
+
+/// 记录一次主仓库推送结果；成功后推送到所有备份镜像
+pub fn record_push_result(config: &Config, result: &Result<()>) {
+    let local_path = config.local_path.as_str();
+    let mut statuses = vec![GitManager::primary_status(local_path, result)];
+
+    match result {
+        Ok(()) => {
+            mark_synced(local_path);
+            statuses.extend(GitManager::push_mirrors_at(config));
+        }
+        Err(e) => record_failure(local_path, e),
+    }
+
+    if let Err(e) = save_remote_status(local_path, &statuses) {
+        eprintln!("保存远程状态失败: {}", e);
+    }
+}
+
+/// 获取远程推送状态文件路径
+pub fn get_remote_status_path(local_path: &str) -> PathBuf {
+    Path::new(local_path).join(".desktop_data").join("remote_status.json")
+}
+
+/// 加载各远程的最近推送状态
+pub fn load_remote_status(local_path: &str) -> Vec<RemoteStatus> {
+    let path = get_remote_status_path(local_path);
+
+    if path.exists() {
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        serde_json::from_str(&content).unwrap_or_default()
+    } else {
+        Vec::new()
+    }
+}
+
+/// 更新远程推送状态（按远程名称合并，保留本次未推送的远程的旧状态）
+fn save_remote_status(local_path: &str, statuses: &[RemoteStatus]) -> Result<()> {
+    let mut all = load_remote_status(local_path);
+
+    for status in statuses {
+        match all.iter_mut().find(|s| s.name == status.name) {
+            Some(existing) => *existing = status.clone(),
+            None => all.push(status.clone()),
+        }
+    }
+
+    // 移除已不存在的镜像
+    let mirrors = GitManager::mirror_remotes_at(local_path).unwrap_or_default();
+    all.retain(|s| s.role == "primary" || mirrors.iter().any(|(name, _)| *name == s.name));
+
+    let path = get_remote_status_path(local_path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(&all)?)?;
+
+    Ok(())
+}
+
+/// 如果有待推送的提交且已到重试时间，先拉取远程更改再推送
+/// 未执行时返回 None；否则返回拉取到的远程更改或错误（推送被拒绝、历史分歧时不再自动重试）
+pub fn retry_pending_push(config: &Config) -> Option<Result<IncomingChanges>> {
+    if !config.sync_enabled() {
+        return None;
+    }
+
+    let journal = load_journal(&config.local_path);
+    if !journal.pending || journal.blocked {
+        return None;
+    }
+
+    let now = Local::now().timestamp();
+    if journal.next_retry_at.is_some_and(|t| t > now) {
+        return None;
+    }
+
+    // 先合并远程的新提交，避免因非快进被拒绝
+    let incoming = match GitManager::pull_at(config) {
+        Ok(incoming) => incoming,
+        Err(e) => {
+            record_failure(&config.local_path, &e);
+            return Some(Err(e));
+        }
+    };
+
+    let result = GitManager::push_at(config);
+    record_push_result(config, &result);
+    Some(result.map(|_| incoming))
+}
+
+/// 启动后台重试线程，retry 在每轮检查时调用（通常获取当前配置后调用 retry_pending_push）
+pub fn spawn_retry_worker<F>(retry: F)
+where
+    F: Fn() + Send + 'static,
+{
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL_INTERVAL);
+        retry();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git").args(args).current_dir(dir).output().unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// 让待推送的提交立即到期
+    fn make_due(local_path: &str) {
+        let mut journal = load_journal(local_path);
+        journal.next_retry_at = Some(0);
+        save_journal(local_path, &journal).unwrap();
+    }
+
+    fn commit_file(git_mgr: &GitManager, name: &str) {
+        fs::write(Path::new(git_mgr.local_path()).join(name), name).unwrap();
+        git_mgr.add_and_commit(name, &format!("更新 {}", name)).unwrap();
+    }
+
+    #[test]
+    fn retries_until_remote_comes_back() {
+        let root = std::env::temp_dir().join(format!("todo-sync-queue-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        git(&root, &["init", "--bare", "-q", "-b", "main", "remote.git"]);
+
+        let remote = root.join("remote.git");
+        let config = Config {
+            local_path: root.join("local").to_string_lossy().to_string(),
+            user_name: "Test".to_string(),
+            user_email: "test@example.com".to_string(),
+            remote_url: Some(remote.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let git_mgr = GitManager::new(config.clone()).unwrap();
+        git_mgr.init().unwrap();
+
+        // 提交即记录到同步日志，到期后推送
+        commit_file(&git_mgr, "a.md");
+        assert!(load_journal(&config.local_path).pending);
+        make_due(&config.local_path);
+        assert!(retry_pending_push(&config).unwrap().is_ok());
+        assert!(!load_journal(&config.local_path).pending);
+
+        // 远程不可用：记录失败并退避
+        commit_file(&git_mgr, "b.md");
+        let moved = root.join("remote.gone");
+        fs::rename(&remote, &moved).unwrap();
+        make_due(&config.local_path);
+        assert!(retry_pending_push(&config).unwrap().is_err());
+        let journal = load_journal(&config.local_path);
+        assert!(journal.pending && !journal.blocked);
+        assert_eq!(journal.attempts, 1);
+        assert!(journal.next_retry_at.unwrap() > Local::now().timestamp());
+
+        // 远程恢复：未到重试时间不推送，到期后推送成功
+        fs::rename(&moved, &remote).unwrap();
+        assert!(retry_pending_push(&config).is_none());
+        make_due(&config.local_path);
+        assert!(retry_pending_push(&config).unwrap().is_ok());
+        assert!(!load_journal(&config.local_path).pending);
+        assert_eq!(git(&remote, &["rev-parse", "main"]), git(Path::new(&config.local_path), &["rev-parse", "HEAD"]));
+
+        // 推送被远程拒绝：停止自动重试
+        fs::write(remote.join("hooks").join("pre-receive"), "#!/bin/sh\nexit 1\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(remote.join("hooks").join("pre-receive"), fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        commit_file(&git_mgr, "c.md");
+        make_due(&config.local_path);
+        assert!(retry_pending_push(&config).unwrap().is_err());
+        let journal = load_journal(&config.local_path);
+        assert!(journal.pending && journal.blocked);
+        make_due(&config.local_path);
+        assert!(retry_pending_push(&config).is_none());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
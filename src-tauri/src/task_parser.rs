@@ -0,0 +1,209 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::ops::Range;
+use crate::config::TaskDiff;
+
+/// 日文件中的一个顶级任务（含其缩进的子任务/子内容）
+#[derive(Debug, Clone)]
+pub struct ParsedTask {
+    /// 任务文本（不含复选框）
+    pub text: String,
+    /// 是否已勾选
+    pub completed: bool,
+    /// 所在区域标题，如 "待办事项"
+    pub section: String,
+    /// 任务行及其子内容的原始行
+    pub lines: Vec<String>,
+    /// `lines` 在文件中的行号范围（从 0 开始）
+    pub line_range: Range<usize>,
+}
+
+/// 解析文件内容中的顶级任务
+pub fn parse_tasks(content: &str) -> Vec<ParsedTask> {
+    let task_regex = Regex::new(r"^-\s*\[([ xX])\]\s*(.+)$").unwrap();
+    let mut tasks: Vec<ParsedTask> = vec![];
+    let mut section = String::new();
+    let mut in_task = false;
+
+    for (index, line) in content.lines().enumerate() {
+        if let Some(title) = line.strip_prefix("## ") {
+            section = title.trim().to_string();
+            in_task = false;
+            continue;
+        }
+
+        // 缩进的行属于上一个任务
+        if line.starts_with("  ") {
+            if in_task {
+                if let Some(task) = tasks.last_mut() {
+                    task.lines.push(line.to_string());
+                    task.line_range.end = index + 1;
+                }
+            }
+            continue;
+        }
+
+        in_task = false;
+        if let Some(caps) = task_regex.captures(line) {
+            let text = caps[2].trim().to_string();
+            if !text.is_empty() {
+                tasks.push(ParsedTask {
+                    text,
+                    completed: &caps[1] != " ",
+                    section: section.clone(),
+                    lines: vec![line.to_string()],
+                    line_range: index..index + 1,
+                });
+                in_task = true;
+            }
+        }
+    }
+
+    tasks
+}
+
+/// 比较两个版本的任务列表（按任务文本匹配）
+pub fn diff_tasks(old_content: &str, new_content: &str) -> TaskDiff {
+    let old_tasks = parse_tasks(old_content);
+    let new_tasks = parse_tasks(new_content);
+
+    let old_map: HashMap<&str, bool> = old_tasks
+        .iter()
+        .map(|t| (t.text.as_str(), t.completed))
+        .collect();
+    let new_map: HashMap<&str, bool> = new_tasks
+        .iter()
+        .map(|t| (t.text.as_str(), t.completed))
+        .collect();
+
+    let mut diff = TaskDiff::default();
+
+    for task in &new_tasks {
+        match old_map.get(task.text.as_str()) {
+            None => push_unique(&mut diff.added, &task.text),
+            Some(false) if task.completed => push_unique(&mut diff.completed, &task.text),
+            Some(true) if !task.completed => push_unique(&mut diff.reopened, &task.text),
+            _ => {}
+        }
+    }
+
+    for task in &old_tasks {
+        if !new_map.contains_key(task.text.as_str()) {
+            push_unique(&mut diff.removed, &task.text);
+        }
+    }
+
+    diff
+}
+
+fn push_unique(list: &mut Vec<String>, text: &str) {
+    if !list.iter().any(|t| t == text) {
+        list.push(text.to_string());
+    }
+}
+
+/// 从文件内容中移除任务的行（即 `task.lines`），其余内容保持不变
+pub fn remove_task(content: &str, task: &ParsedTask) -> String {
+    let mut lines: Vec<&str> = content.lines().collect();
+    lines.drain(task.line_range.clone());
+
+    let mut result = lines.join("\n");
+    if content.ends_with('\n') && !result.is_empty() {
+        result.push('\n');
+    }
+    result
+}
+
+/// 将任务行插入到指定区域的末尾，区域不存在时追加到文件末尾
+pub fn insert_task(content: &str, section: &str, task_lines: &[String]) -> String {
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+    let heading = format!("## {}", section);
+
+    let insert_at = match lines.iter().position(|l| l.trim() == heading) {
+        Some(start) => {
+            // 区域结束于下一个标题
+            let mut end = lines[start + 1..]
+                .iter()
+                .position(|l| l.starts_with("## "))
+                .map(|i| start + 1 + i)
+                .unwrap_or(lines.len());
+            // 跳过区域末尾的空行，插入到最后一项之后
+            while end > start + 1 && lines[end - 1].trim().is_empty() {
+                end -= 1;
+            }
+            end
+        }
+        None => {
+            if section.is_empty() {
+                lines.len()
+            } else {
+                if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                    lines.push(String::new());
+                }
+                lines.push(heading);
+                lines.len()
+            }
+        }
+    };
+
+    for (i, line) in task_lines.iter().enumerate() {
+        lines.insert(insert_at + i, line.clone());
+    }
+
+    let mut result = lines.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TrashEntry;
+    use crate::trash;
+
+    #[test]
+    fn deleted_task_round_trips_through_trash() {
+        let content = "# 2026-10-18\n\n## 待办事项\n- [ ] 写周报\n  - [ ] 收集数据\n  备注\n\n  空行后的段落\n- [ ] 买菜\n\n## 已完成\n- [x] 晨跑\n";
+
+        // 删除：只移除解析出的任务行，空行及其后的缩进段落保留
+        let task = parse_tasks(content).into_iter().find(|t| t.text == "写周报").unwrap();
+        assert_eq!(task.lines, vec!["- [ ] 写周报", "  - [ ] 收集数据", "  备注"]);
+        let remaining = remove_task(content, &task);
+        assert_eq!(
+            remaining,
+            "# 2026-10-18\n\n## 待办事项\n\n  空行后的段落\n- [ ] 买菜\n\n## 已完成\n- [x] 晨跑\n"
+        );
+
+        // 记入回收站
+        let dir = std::env::temp_dir().join(format!("todo-task-trash-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let local_path = dir.to_string_lossy().to_string();
+        let entry = TrashEntry {
+            id: trash::generate_entry_id("2026/10/18.md", &task.text),
+            kind: "task".to_string(),
+            filepath: "2026/10/18.md".to_string(),
+            source_date: Some("2026-10-18".to_string()),
+            text: Some(task.text.clone()),
+            section: Some(task.section.clone()),
+            lines: task.lines.clone(),
+            commit: None,
+            previous_commit: None,
+            deleted_at: trash::now(),
+        };
+        trash::record(&local_path, vec![entry], 30).unwrap();
+        let entry = trash::load_trash(&local_path).entries.remove(0);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // 恢复：任务及全部子内容回到原区域，其他内容不受影响
+        let restored = insert_task(&remaining, entry.section.as_deref().unwrap(), &entry.lines);
+        assert_eq!(
+            restored,
+            "# 2026-10-18\n\n## 待办事项\n\n  空行后的段落\n- [ ] 买菜\n- [ ] 写周报\n  - [ ] 收集数据\n  备注\n\n## 已完成\n- [x] 晨跑\n"
+        );
+        let restored_task = parse_tasks(&restored).into_iter().find(|t| t.text == "写周报").unwrap();
+        assert_eq!(restored_task.section, "待办事项");
+        assert_eq!(restored_task.lines, task.lines);
+    }
+}
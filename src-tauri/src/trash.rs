@@ -0,0 +1,77 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::config::{TrashData, TrashEntry};
+
+/// 获取回收站文件路径
+pub fn get_trash_path(local_path: &str) -> PathBuf {
+    Path::new(local_path).join(".desktop_data").join("trash.json")
+}
+
+/// 加载回收站（文件不存在或损坏时返回空回收站）
+pub fn load_trash(local_path: &str) -> TrashData {
+    let path = get_trash_path(local_path);
+
+    if path.exists() {
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        serde_json::from_str(&content).unwrap_or_default()
+    } else {
+        TrashData::default()
+    }
+}
+
+/// 保存回收站
+pub fn save_trash(local_path: &str, data: &TrashData) -> Result<()> {
+    let path = get_trash_path(local_path);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let content = serde_json::to_string_pretty(data)?;
+    fs::write(&path, content)?;
+
+    // 注意：.desktop_data/trash.json 不提交到 git（本地客户端数据不同步）
+
+    Ok(())
+}
+
+/// 移除超过保留天数的条目，返回移除数量
+pub fn purge_expired(data: &mut TrashData, retention_days: u32) -> usize {
+    if retention_days == 0 {
+        return 0;
+    }
+
+    let cutoff = Local::now() - Duration::days(retention_days as i64);
+    let before = data.entries.len();
+
+    data.entries.retain(|entry| {
+        DateTime::parse_from_rfc3339(&entry.deleted_at)
+            .map(|t| t >= cutoff)
+            .unwrap_or(true)
+    });
+
+    before - data.entries.len()
+}
+
+/// 记录删除操作，同时清理过期条目
+pub fn record(local_path: &str, entries: Vec<TrashEntry>, retention_days: u32) -> Result<()> {
+    let mut data = load_trash(local_path);
+    purge_expired(&mut data, retention_days);
+    data.entries.extend(entries);
+    save_trash(local_path, &data)
+}
+
+/// 生成回收站条目ID
+pub fn generate_entry_id(filepath: &str, name: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let nanos = Local::now().timestamp_nanos_opt().unwrap_or_default();
+    let digest = Sha256::digest(format!("{}:{}:{}", filepath, name, nanos).as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 当前时间（RFC 3339），用于 deleted_at
+pub fn now() -> String {
+    Local::now().to_rfc3339()
+}
@@ -1,6 +1,7 @@
 import { useState, useEffect, useCallback } from 'react';
 import { Modal, Button, Typography, Space, message } from 'antd';
 import { invoke } from '@tauri-apps/api/tauri';
+import { formatError } from '../store/configStore';
 import './ConflictResolver.css';
 
 const { Text, Title } = Typography;
@@ -82,12 +83,12 @@ export default function ConflictResolver({
         message.success(`已解决 ${currentFile}，还剩 ${conflictFiles.length - currentFileIndex - 1} 个文件`);
       } else {
         // 所有冲突已解决，完成合并
-        await invoke('complete_merge', { message: '解决合并冲突' });
+        await invoke('complete_merge');
         message.success('所有冲突已解决');
         onResolved();
       }
     } catch (error) {
-      message.error(`解决冲突失败: ${error}`);
+      message.error(`解决冲突失败: ${formatError(error)}`);
     } finally {
       setLoading(false);
     }
@@ -0,0 +1,99 @@
+import { useState } from 'react';
+import { Modal, Button, Radio, Space, Typography, Alert, message } from 'antd';
+import { invoke } from '@tauri-apps/api/tauri';
+import { Divergence, DivergenceChoice, DivergenceResolution } from '../store/configStore';
+
+const { Text, Paragraph } = Typography;
+
+interface DivergenceResolverProps {
+  divergence: Divergence | null;
+  onResolved: (resolution: DivergenceResolution) => void;
+  onMergeConflict: () => void;
+  onCancel: () => void;
+}
+
+const CHOICES: { value: DivergenceChoice; label: string; description: string }[] = [
+  {
+    value: 'merge_unrelated',
+    label: '合并两份历史',
+    description: '保留本地和远程的所有内容，可能需要手动解决冲突',
+  },
+  {
+    value: 'take_remote',
+    label: '采用远程历史',
+    description: '本地切换到远程版本，本地独有的提交保存在备份引用中',
+  },
+  {
+    value: 'force_push',
+    label: '保留本地并强制推送',
+    description: '用本地历史覆盖远程，远程独有的提交保存在备份引用中',
+  },
+];
+
+export default function DivergenceResolver({
+  divergence,
+  onResolved,
+  onMergeConflict,
+  onCancel,
+}: DivergenceResolverProps) {
+  const [choice, setChoice] = useState<DivergenceChoice>('merge_unrelated');
+  const [loading, setLoading] = useState(false);
+
+  const handleConfirm = async () => {
+    setLoading(true);
+    try {
+      const resolution = await invoke<DivergenceResolution>('resolve_divergence', { choice });
+      message.success(`已处理，备份引用: ${resolution.backupRefs.join(', ')}`);
+      onResolved(resolution);
+    } catch (error) {
+      const errorMsg = String(error);
+      if (errorMsg.includes('冲突')) {
+        onMergeConflict();
+      } else {
+        message.error(`处理失败: ${errorMsg}`);
+      }
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const summary =
+    divergence?.kind === 'unrelated'
+      ? `远程分支 ${divergence.branch} 与本地没有共同的提交（远程仓库可能已被重建）。本地 ${divergence.ahead} 个提交，远程 ${divergence.behind} 个提交。`
+      : `远程分支 ${divergence?.branch} 的历史已被改写（强制推送），${divergence?.dropped} 个已同步过的提交不在远程中。`;
+
+  return (
+    <Modal
+      title="远程历史与本地不一致"
+      open={!!divergence}
+      onCancel={onCancel}
+      footer={[
+        <Button key="cancel" onClick={onCancel}>
+          稍后处理
+        </Button>,
+        <Button key="confirm" type="primary" danger={choice !== 'merge_unrelated'} loading={loading} onClick={handleConfirm}>
+          确定
+        </Button>,
+      ]}
+    >
+      <Paragraph>{summary}</Paragraph>
+      <Alert
+        type="info"
+        showIcon
+        style={{ marginBottom: 16 }}
+        message="无论选择哪种方式，操作前都会为本地和远程分支创建备份引用（refs/todo-backup/）"
+      />
+      <Radio.Group value={choice} onChange={(e) => setChoice(e.target.value)}>
+        <Space direction="vertical">
+          {CHOICES.map((item) => (
+            <Radio key={item.value} value={item.value}>
+              <Text strong>{item.label}</Text>
+              <br />
+              <Text type="secondary">{item.description}</Text>
+            </Radio>
+          ))}
+        </Space>
+      </Radio.Group>
+    </Modal>
+  );
+}
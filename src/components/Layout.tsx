@@ -6,6 +6,7 @@ import { useConfigStore } from '../store/configStore';
 import { useAutoSync } from '../hooks/useAutoSync';
 import Sidebar from './Sidebar';
 import ConflictResolver from './ConflictResolver';
+import DivergenceResolver from './DivergenceResolver';
 import './Layout.css';
 
 export default function Layout() {
@@ -21,6 +22,10 @@ export default function Layout() {
     showConflictResolver,
     handleConflictResolved,
     handleConflictCancel,
+    divergence,
+    handleDivergenceResolved,
+    handleDivergenceConflict,
+    handleDivergenceCancel,
   } = useAutoSync({
     onSyncStart: () => setSyncing(true),
     onSyncEnd: () => setSyncing(false),
@@ -95,6 +100,14 @@ export default function Layout() {
         onResolved={handleConflictResolved}
         onCancel={handleConflictCancel}
       />
+
+      {/* 远程历史被改写或无关时的处理 */}
+      <DivergenceResolver
+        divergence={divergence}
+        onResolved={handleDivergenceResolved}
+        onMergeConflict={handleDivergenceConflict}
+        onCancel={handleDivergenceCancel}
+      />
     </div>
   );
 }
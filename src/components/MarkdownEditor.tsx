@@ -1,7 +1,7 @@
 import { useEffect, useRef, useCallback, useState, useMemo } from 'react';
 import { invoke, convertFileSrc } from '@tauri-apps/api/tauri';
 import { message, Modal } from 'antd';
-import { useConfigStore } from '../store/configStore';
+import { useConfigStore, formatError } from '../store/configStore';
 import { useStatsStore } from '../store/statsStore';
 import {
   PlusOutlined,
@@ -989,7 +989,7 @@ export default function MarkdownEditor({
         paths,
       });
     } catch (error) {
-      console.error('删除附件失败:', error);
+      message.error(`删除附件失败: ${formatError(error)}`);
     }
   }, [year, month]);
 
@@ -1101,7 +1101,7 @@ export default function MarkdownEditor({
           results.push(`[${file.name}](${relativePath})`);
         }
       } catch (error) {
-        message.error(`上传 ${file.name} 失败: ${error}`);
+        message.error(`上传 ${file.name} 失败: ${formatError(error)}`);
       }
     }
 
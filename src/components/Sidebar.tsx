@@ -7,7 +7,7 @@ import { getVersion } from '@tauri-apps/api/app';
 import { appWindow } from '@tauri-apps/api/window';
 import { open } from '@tauri-apps/api/shell';
 import dayjs from 'dayjs';
-import { useConfigStore } from '../store/configStore';
+import { useConfigStore, syncEnabled, formatError } from '../store/configStore';
 import { useStatsStore } from '../store/statsStore';
 import { usePastUncompletedStore, PastUncompletedTask } from '../store/pastUncompletedStore';
 import './Sidebar.css';
@@ -149,8 +149,8 @@ export default function Sidebar({ selectedDate, onDateSelect, onSync, syncing, o
   const handleDeleteTask = (task: PastUncompletedTask) => {
     message.success('已删除');
     // 后台执行，不等待
-    deleteTask(task).catch(() => {
-      message.error('删除失败，请重试');
+    deleteTask(task).catch((error) => {
+      message.error(`删除失败: ${formatError(error)}`);
     });
   };
 
@@ -311,7 +311,7 @@ export default function Sidebar({ selectedDate, onDateSelect, onSync, syncing, o
             icon={<SyncOutlined spin={syncing} />}
             onClick={onSync}
             loading={syncing}
-            disabled={!isConfigured || !config?.remoteUrl}
+            disabled={!isConfigured || !syncEnabled(config)}
             size="small"
           >
             同步
@@ -1,7 +1,8 @@
 import { useEffect, useRef, useCallback, useState } from 'react';
 import { invoke } from '@tauri-apps/api/tauri';
-import { message } from 'antd';
-import { useConfigStore } from '../store/configStore';
+import { listen } from '@tauri-apps/api/event';
+import { message, notification } from 'antd';
+import { useConfigStore, IncomingChanges, PushResult, PullError, Divergence, DivergenceResolution, syncEnabled } from '../store/configStore';
 
 interface UseAutoSyncOptions {
   onSyncStart?: () => void;
@@ -10,11 +11,60 @@ interface UseAutoSyncOptions {
   onConflict?: (files: string[]) => void;
 }
 
+/** 命令错误的文字信息（git_pull 返回结构化的 PullError） */
+function errorMessage(error: unknown): string {
+  if (error && typeof error === 'object' && 'message' in error) {
+    return String((error as PullError).message);
+  }
+  return String(error);
+}
+
+/** 推送已成功但发布站点失败时单独提示 */
+function notifyPagesError(result: PushResult) {
+  if (result.pagesError) {
+    notification.warning({
+      message: '推送成功，但发布站点失败',
+      description: result.pagesError,
+    });
+  }
+}
+
+/** 生成远程更改摘要，如 "Laptop 在 10-16 完成了 3 个任务" */
+function describeIncoming(changes: IncomingChanges): string | null {
+  const parts = changes.days
+    .map(({ date, tasks }) => {
+      const actions = [
+        tasks.completed.length > 0 && `完成了 ${tasks.completed.length} 个任务`,
+        tasks.added.length > 0 && `新增了 ${tasks.added.length} 个任务`,
+        tasks.removed.length > 0 && `删除了 ${tasks.removed.length} 个任务`,
+        tasks.reopened.length > 0 && `重新打开了 ${tasks.reopened.length} 个任务`,
+      ].filter(Boolean);
+      return actions.length > 0 ? `在 ${date.slice(5)} ${actions.join('，')}` : null;
+    })
+    .filter(Boolean);
+
+  if (parts.length === 0) {
+    return null;
+  }
+  const names = changes.devices.length > 0 ? changes.devices : changes.authors;
+  const who = names.length > 0 ? names.join('、') : '其他设备';
+  return `${who} ${parts.join('；')}`;
+}
+
+function notifyIncoming(changes: IncomingChanges) {
+  const description = describeIncoming(changes);
+  if (description) {
+    notification.info({ message: '收到远程更改', description });
+  }
+}
+
 export function useAutoSync(options: UseAutoSyncOptions = {}) {
   const { isConfigured, config, notifySyncComplete } = useConfigStore();
+  const canSync = syncEnabled(config);
   const isSyncingRef = useRef(false);
   const [conflictFiles, setConflictFiles] = useState<string[]>([]);
   const [showConflictResolver, setShowConflictResolver] = useState(false);
+  const [divergence, setDivergence] = useState<Divergence | null>(null);
 
   const checkConflicts = useCallback(async (): Promise<boolean> => {
     try {
@@ -34,9 +84,19 @@ export function useAutoSync(options: UseAutoSyncOptions = {}) {
     return false;
   }, [options]);
 
+  // 拉取因远程历史被改写或无关而中止时，让用户选择处理方式
+  const checkDivergence = useCallback((error: unknown): boolean => {
+    const divergence = (error as Partial<PullError> | null)?.divergence;
+    if (!divergence) {
+      return false;
+    }
+    setDivergence(divergence);
+    return true;
+  }, []);
+
   // 手动同步（点击同步按钮时调用）
   const sync = useCallback(async (silent = false) => {
-    if (!isConfigured || !config?.remoteUrl || isSyncingRef.current) {
+    if (!isConfigured || !canSync || isSyncingRef.current) {
       return;
     }
 
@@ -60,7 +120,7 @@ export function useAutoSync(options: UseAutoSyncOptions = {}) {
       await savePromise;
 
       // 2. 拉取远程更新
-      await invoke('git_pull');
+      notifyIncoming(await invoke<IncomingChanges>('git_pull'));
 
       // 3. 检查是否有冲突
       const hasConflict = await checkConflicts();
@@ -69,7 +129,7 @@ export function useAutoSync(options: UseAutoSyncOptions = {}) {
       }
 
       // 4. 推送本地更改
-      await invoke('git_push');
+      notifyPagesError(await invoke<PushResult>('git_push'));
 
       // 5. 同步成功，通知重新加载数据
       notifySyncComplete();
@@ -78,8 +138,10 @@ export function useAutoSync(options: UseAutoSyncOptions = {}) {
         message.success('同步成功');
       }
     } catch (error) {
-      const errorMsg = String(error);
-      if (errorMsg.includes('conflict') || errorMsg.includes('冲突')) {
+      const errorMsg = errorMessage(error);
+      if (checkDivergence(error)) {
+        // 由 DivergenceResolver 处理
+      } else if (errorMsg.includes('conflict') || errorMsg.includes('冲突')) {
         await checkConflicts();
       } else if (!silent) {
         message.error(`同步失败: ${errorMsg}`);
@@ -89,13 +151,14 @@ export function useAutoSync(options: UseAutoSyncOptions = {}) {
       isSyncingRef.current = false;
       options.onSyncEnd?.();
     }
-  }, [isConfigured, config?.remoteUrl, options, checkConflicts, notifySyncComplete]);
+  }, [isConfigured, canSync, options, checkConflicts, checkDivergence, notifySyncComplete]);
 
   const handleConflictResolved = useCallback(() => {
     setShowConflictResolver(false);
     setConflictFiles([]);
-    invoke('git_push')
-      .then(() => {
+    invoke<PushResult>('git_push')
+      .then((result) => {
+        notifyPagesError(result);
         notifySyncComplete();
         message.success('同步成功');
       })
@@ -109,22 +172,64 @@ export function useAutoSync(options: UseAutoSyncOptions = {}) {
 
   // 启动时自动拉取（只拉取不推送）
   useEffect(() => {
-    if (isConfigured && config?.remoteUrl) {
+    if (isConfigured && canSync) {
       const timer = setTimeout(() => {
-        invoke('git_pull')
-          .then(() => {
+        invoke<IncomingChanges>('git_pull')
+          .then((changes) => {
+            notifyIncoming(changes);
             notifySyncComplete();
             return checkConflicts();
           })
-          .catch(() => {});
+          .catch((error) => checkDivergence(error));
       }, 2000);
 
       return () => clearTimeout(timer);
     }
-  }, [isConfigured, config?.remoteUrl, checkConflicts, notifySyncComplete]);
+  }, [isConfigured, canSync, checkConflicts, checkDivergence, notifySyncComplete]);
+
+  // 后台同步（托盘、关闭窗口）拉取到的远程更改
+  useEffect(() => {
+    const unlisten = listen<IncomingChanges>('incoming-changes', (event) => {
+      notifyIncoming(event.payload);
+      notifySyncComplete();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [notifySyncComplete]);
+
+  // 后台同步发现远程历史被改写
+  useEffect(() => {
+    const unlisten = listen<Divergence>('history-diverged', (event) => {
+      setDivergence(event.payload);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  const handleDivergenceResolved = useCallback((resolution: DivergenceResolution) => {
+    setDivergence(null);
+    notifyIncoming(resolution.incoming);
+    notifySyncComplete();
+  }, [notifySyncComplete]);
+
+  const handleDivergenceConflict = useCallback(() => {
+    setDivergence(null);
+    checkConflicts();
+  }, [checkConflicts]);
+
+  const handleDivergenceCancel = useCallback(() => {
+    setDivergence(null);
+    message.warning('远程历史不一致，同步已暂停');
+  }, []);
 
   return {
     sync,
+    divergence,
+    handleDivergenceResolved,
+    handleDivergenceConflict,
+    handleDivergenceCancel,
     conflictFiles,
     showConflictResolver,
     handleConflictResolved,
@@ -3,7 +3,7 @@ import { useParams } from 'react-router-dom';
 import { Typography, message } from 'antd';
 import { invoke } from '@tauri-apps/api/tauri';
 import dayjs from 'dayjs';
-import { useConfigStore } from '../store/configStore';
+import { useConfigStore, syncEnabled, PushResult, formatError } from '../store/configStore';
 import { usePastUncompletedStore } from '../store/pastUncompletedStore';
 import MarkdownEditor from '../components/MarkdownEditor';
 import './DayView.css';
@@ -16,6 +16,7 @@ const IDLE_SAVE_TIMEOUT = 3 * 60 * 1000;
 export default function DayView() {
   const { date } = useParams<{ date: string }>();
   const { isConfigured, syncVersion, config } = useConfigStore();
+  const canSync = syncEnabled(config);
   const { executePendingDeletions } = usePastUncompletedStore();
   const [content, setContent] = useState('');
   const [saving, setSaving] = useState(false);
@@ -59,14 +60,17 @@ export default function DayView() {
 
   // 同步到远程（git push）
   const syncToRemote = useCallback(async () => {
-    if (!config?.remoteUrl) return;
+    if (!canSync) return;
 
     try {
-      await invoke('git_push');
+      const result = await invoke<PushResult>('git_push');
+      if (result.pagesError) {
+        console.error('发布站点失败:', result.pagesError);
+      }
     } catch (error) {
       console.error('同步失败:', error);
     }
-  }, [config?.remoteUrl]);
+  }, [canSync]);
 
   // 保存并同步（主动保存时使用）
   const handleSave = useCallback(async (silent = false) => {
@@ -104,7 +108,7 @@ export default function DayView() {
       }
     } catch (error) {
       if (!silent) {
-        message.error(`保存失败: ${error}`);
+        message.error(`保存失败: ${formatError(error)}`);
       }
     } finally {
       setSaving(false);
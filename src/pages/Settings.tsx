@@ -1,16 +1,51 @@
 import { useState, useEffect } from 'react';
-import { Form, Input, Button, Select, Switch, Card, message, Space, Alert, Radio } from 'antd';
-import { FolderOpenOutlined, SaveOutlined, CloudDownloadOutlined, ImportOutlined, ArrowLeftOutlined, EditOutlined } from '@ant-design/icons';
-import { useConfigStore, Config } from '../store/configStore';
+import { Form, Input, InputNumber, Button, Select, Switch, Card, message, Space, Alert, Radio, Progress } from 'antd';
+import { FolderOpenOutlined, SaveOutlined, CloudDownloadOutlined, CloudUploadOutlined, ImportOutlined, ArrowLeftOutlined, EditOutlined } from '@ant-design/icons';
+import { useConfigStore, Config, FolderScan, SigningConfig, formatError } from '../store/configStore';
 import { open } from '@tauri-apps/api/dialog';
 import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
 import { useNavigate } from 'react-router-dom';
 import './Settings.css';
 
 const { Option } = Select;
 
+const COMMIT_ACTION_LABELS: Record<string, string> = {
+  update: '保存文件',
+  upload_attachment: '上传附件',
+  delete_attachment: '删除附件',
+  delete_task: '删除往期任务',
+  restore_file: '恢复文件',
+  restore_task: '恢复任务',
+  resolve_conflicts: '解决冲突',
+  merge: '合并远程更改',
+  import: '导入笔记',
+  init: '初始化仓库',
+  gitignore: '更新 .gitignore',
+  untrack_desktop_data: '停止跟踪本地数据',
+  publish_pages: '发布站点',
+};
+
 type DataSourceType = 'clone' | 'import';
 
+interface CloneProgress {
+  stage: 'receiving' | 'resolving' | 'checkout';
+  receivedObjects: number;
+  totalObjects: number;
+  receivedBytes: number;
+  indexedDeltas: number;
+  totalDeltas: number;
+  checkoutFiles: number;
+  totalCheckoutFiles: number;
+  percent: number;
+}
+
+const CLONE_STAGE_LABELS: Record<CloneProgress['stage'], string> = {
+  receiving: '接收对象',
+  resolving: '解析增量',
+  checkout: '检出文件',
+};
+
 interface GitInfo {
   userName: string | null;
   userEmail: string | null;
@@ -20,14 +55,18 @@ interface GitInfo {
 
 export default function Settings() {
   const navigate = useNavigate();
-  const { config, saveConfig, initGit, isConfigured } = useConfigStore();
+  const { config, saveConfig, initGit, adoptFolder, attachRemote, isConfigured } = useConfigStore();
   const [form] = Form.useForm();
   const [loading, setLoading] = useState(false);
+  const [attaching, setAttaching] = useState(false);
   const [cloning, setCloning] = useState(false);
+  const [cloneProgress, setCloneProgress] = useState<CloneProgress | null>(null);
+  const [folderScan, setFolderScan] = useState<FolderScan | null>(null);
   const [detecting, setDetecting] = useState(false);
   const [dataSource, setDataSource] = useState<DataSourceType>('clone');
   const [localPath, setLocalPath] = useState('');
   const [editMode, setEditMode] = useState(false);
+  const [builtinTemplates, setBuiltinTemplates] = useState<Record<string, string>>({});
 
   // 如果未配置，默认进入编辑模式
   useEffect(() => {
@@ -43,6 +82,10 @@ export default function Settings() {
     }
   }, [config, form]);
 
+  useEffect(() => {
+    loadCommitTemplates(config?.commitTemplates?.style || 'zh');
+  }, [config?.commitTemplates?.style]);
+
   const handleSelectFolder = async () => {
     try {
       const selected = await open({
@@ -70,9 +113,13 @@ export default function Settings() {
     try {
       const isGitRepo = await invoke<boolean>('is_git_repo', { path });
       if (!isGitRepo) {
-        message.warning('所选目录不是 Git 仓库');
+        // 不是 Git 仓库：扫描现有 Markdown 文件，保存时作为一次导入提交
+        const scan = await invoke<FolderScan>('scan_markdown_folder', { path });
+        setFolderScan(scan);
+        message.info(`所选目录不是 Git 仓库，保存配置时将导入其中的 ${scan.matched.length + scan.convertible.length + scan.unmatched.length} 个 Markdown 文件`);
         return;
       }
+      setFolderScan(null);
 
       const gitInfo = await invoke<GitInfo | null>('detect_git_config', { path });
       if (gitInfo) {
@@ -102,6 +149,7 @@ export default function Settings() {
     const remoteUrl = form.getFieldValue('remoteUrl');
     const basePath = localPath;
     const token = form.getFieldValue('token');
+    const depth = form.getFieldValue('cloneDepth');
 
     if (!remoteUrl) {
       message.error('请输入远程仓库地址');
@@ -113,12 +161,17 @@ export default function Settings() {
     }
 
     setCloning(true);
+    setCloneProgress(null);
+    const unlisten = await listen<CloneProgress>('clone-progress', (event) => {
+      setCloneProgress(event.payload);
+    });
     try {
       // clone_repo 返回实际克隆的路径（包含仓库名子目录）
       const actualPath = await invoke<string>('clone_repo', {
         url: remoteUrl,
         path: basePath,
         token: token || null,
+        depth: depth || null,
       });
 
       // 更新 localPath 为实际克隆路径
@@ -156,18 +209,82 @@ export default function Settings() {
     } catch (error) {
       message.error(`克隆失败: ${error}`);
     } finally {
+      unlisten();
       setCloning(false);
+      setCloneProgress(null);
+    }
+  };
+
+  const loadCommitTemplates = async (style: string) => {
+    try {
+      setBuiltinTemplates(await invoke<Record<string, string>>('get_commit_templates', { style }));
+    } catch (error) {
+      console.error('Failed to load commit templates:', error);
+    }
+  };
+
+  const handleAttachRemote = async () => {
+    const remoteUrl = form.getFieldValue('remoteUrl');
+    if (!remoteUrl) {
+      message.warning('请先填写远程仓库地址');
+      return;
+    }
+
+    setAttaching(true);
+    try {
+      const result = await attachRemote(remoteUrl, form.getFieldValue('token') || undefined, form.getFieldValue('gitProvider'));
+      const outcome = {
+        pushed: '已推送到空的远程仓库',
+        up_to_date: '本地与远程已一致',
+        fast_forward: '已获取远程历史',
+        merged: '已合并远程历史',
+        merged_unrelated: '已合并远程仓库中的独立历史',
+      }[result.outcome];
+      message.success(`已连接远程仓库（分支 ${result.branch}）：${outcome}`);
+    } catch (error) {
+      message.error(`连接远程仓库失败: ${error}`);
+    } finally {
+      setAttaching(false);
     }
   };
 
-  const handleSubmit = async (values: Config) => {
+  const handleTestSigning = async () => {
+    const signing: SigningConfig = { enabled: true, format: 'gpg', ...form.getFieldValue('signing') };
+    try {
+      await invoke('test_commit_signing', { signing });
+      message.success('签名测试成功');
+    } catch (error) {
+      message.error(formatError(error));
+    }
+  };
+
+  const handleCancelClone = async () => {
+    try {
+      await invoke('cancel_clone');
+    } catch (error) {
+      message.error(`取消失败: ${error}`);
+    }
+  };
+
+  const handleSubmit = async (formValues: Config) => {
     setLoading(true);
     try {
+      // 保留表单中没有的配置项（如备份镜像）
+      const values: Config = { ...config, ...formValues };
+
       // Save config
       await saveConfig(values);
 
-      // Initialize Git
-      await initGit(values);
+      if (dataSource === 'import' && folderScan && !isConfigured) {
+        // 导入现有文件夹（单次导入提交）
+        const convert = form.getFieldValue('convertNearMatches') ?? true;
+        const result = await adoptFolder(values, convert);
+        setFolderScan(null);
+        message.success(`已导入 ${result.importedFiles} 个文件（${result.dayFiles} 个日文件）`);
+      } else {
+        // Initialize Git
+        await initGit(values);
+      }
 
       message.success('配置保存成功');
       setEditMode(false);
@@ -236,6 +353,9 @@ export default function Settings() {
           initialValues={{
             gitProvider: 'github',
             enableGithubPages: false,
+            runGitHooks: true,
+            signing: { enabled: false, format: 'gpg' },
+            commitTemplates: { style: 'zh', overrides: {} },
           }}
         >
           {/* Data Source Selection - only show when not configured */}
@@ -282,6 +402,14 @@ export default function Settings() {
               >
                 <Input.Password placeholder="留空则使用系统凭据" />
               </Form.Item>
+
+              <Form.Item
+                label="克隆深度"
+                name="cloneDepth"
+                tooltip="只获取最近的若干个提交，适合历史和附件很多的仓库；留空则克隆完整历史"
+              >
+                <InputNumber min={1} placeholder="完整历史" style={{ width: '100%' }} />
+              </Form.Item>
             </>
           )}
 
@@ -307,6 +435,37 @@ export default function Settings() {
             </Space.Compact>
           </Form.Item>
 
+          {/* Markdown 文件夹导入预览 */}
+          {dataSource === 'import' && folderScan && !isConfigured && (
+            <Form.Item>
+              <Alert
+                type="info"
+                showIcon
+                message="将导入现有 Markdown 文件夹"
+                description={
+                  <div>
+                    <div>符合 年/月/月-日.md 结构的日文件：{folderScan.matched.length} 个</div>
+                    <div>可转换的文件（如 2026-10-17.md）：{folderScan.convertible.length} 个</div>
+                    {folderScan.conflicts.length > 0 && (
+                      <div>与已有日期冲突、保持原样的文件：{folderScan.conflicts.map((c) => c.from).join('、')}</div>
+                    )}
+                    <div>其他 Markdown 文件（原样导入）：{folderScan.unmatched.length} 个</div>
+                  </div>
+                }
+              />
+              {folderScan.convertible.length > 0 && (
+                <Form.Item
+                  name="convertNearMatches"
+                  valuePropName="checked"
+                  initialValue={true}
+                  style={{ marginTop: 12, marginBottom: 0 }}
+                >
+                  <Switch checkedChildren="转换为日文件" unCheckedChildren="保持原文件名" />
+                </Form.Item>
+              )}
+            </Form.Item>
+          )}
+
           {/* Clone Button */}
           {dataSource === 'clone' && !isConfigured && (
             <Form.Item>
@@ -319,6 +478,21 @@ export default function Settings() {
               >
                 {cloning ? '正在克隆...' : '克隆仓库'}
               </Button>
+              {cloning && (
+                <div style={{ marginTop: 12 }}>
+                  <Progress percent={cloneProgress?.percent ?? 0} size="small" />
+                  <Space style={{ width: '100%', justifyContent: 'space-between' }}>
+                    <span style={{ color: '#888' }}>
+                      {cloneProgress
+                        ? `${CLONE_STAGE_LABELS[cloneProgress.stage]} · ${(cloneProgress.receivedBytes / 1024 / 1024).toFixed(1)} MB`
+                        : '正在连接...'}
+                    </span>
+                    <Button size="small" danger onClick={handleCancelClone}>
+                      取消
+                    </Button>
+                  </Space>
+                </div>
+              )}
             </Form.Item>
           )}
 
@@ -341,6 +515,14 @@ export default function Settings() {
             <Input placeholder="your.email@example.com" />
           </Form.Item>
 
+          <Form.Item
+            label="设备名称"
+            name="deviceName"
+            tooltip="记录在每次提交中，用于区分多台设备的修改（留空使用主机名）"
+          >
+            <Input placeholder="如 台式机、笔记本" />
+          </Form.Item>
+
           <Form.Item
             label="Git 托管平台"
             name="gitProvider"
@@ -353,6 +535,15 @@ export default function Settings() {
             </Select>
           </Form.Item>
 
+          <Form.Item
+            label="仅本地模式"
+            name="localOnly"
+            valuePropName="checked"
+            tooltip="不拉取、不推送，数据只保存在本机 Git 仓库中；之后可随时连接远程仓库"
+          >
+            <Switch />
+          </Form.Item>
+
           {/* Remote URL for import modes or when configured */}
           {(dataSource !== 'clone' || isConfigured) && (
             <Form.Item
@@ -375,6 +566,88 @@ export default function Settings() {
             </Form.Item>
           )}
 
+          {isConfigured && config?.localOnly && (
+            <Form.Item extra="合并远程仓库中已有的历史（远程为空时直接推送），并退出仅本地模式">
+              <Button icon={<CloudUploadOutlined />} onClick={handleAttachRemote} loading={attaching} disabled={false}>
+                连接远程仓库
+              </Button>
+            </Form.Item>
+          )}
+
+          <Form.Item
+            label="执行仓库 Git 钩子"
+            name="runGitHooks"
+            valuePropName="checked"
+            tooltip="提交前后执行仓库中的 pre-commit、commit-msg 等钩子"
+          >
+            <Switch />
+          </Form.Item>
+
+          <Form.Item
+            label="提交信息风格"
+            name={['commitTemplates', 'style']}
+            tooltip="模板中可使用 {date} {time} {file} {action} {task} {device} {count} {renamed} {commit} 占位符"
+          >
+            <Select onChange={(style: string) => loadCommitTemplates(style)}>
+              <Option value="zh">中文</Option>
+              <Option value="en">English</Option>
+              <Option value="conventional">Conventional Commits</Option>
+            </Select>
+          </Form.Item>
+
+          <Form.Item label="自定义提交信息" tooltip="留空时使用所选风格的内置模板">
+            {Object.entries(builtinTemplates).map(([action, template]) => (
+              <Form.Item
+                key={action}
+                name={['commitTemplates', 'overrides', action]}
+                style={{ marginBottom: 8 }}
+              >
+                <Input addonBefore={COMMIT_ACTION_LABELS[action] ?? action} placeholder={template} />
+              </Form.Item>
+            ))}
+          </Form.Item>
+
+          <Form.Item
+            label="提交签名"
+            name={['signing', 'enabled']}
+            valuePropName="checked"
+            tooltip="为应用创建的所有提交签名，签名失败时不会提交"
+          >
+            <Switch />
+          </Form.Item>
+
+          <Form.Item noStyle shouldUpdate={(prev, cur) => prev.signing?.enabled !== cur.signing?.enabled}>
+            {({ getFieldValue }) =>
+              getFieldValue(['signing', 'enabled']) && (
+                <>
+                  <Form.Item label="签名格式" name={['signing', 'format']}>
+                    <Radio.Group>
+                      <Radio value="gpg">GPG</Radio>
+                      <Radio value="ssh">SSH</Radio>
+                    </Radio.Group>
+                  </Form.Item>
+                  <Form.Item
+                    label="签名密钥"
+                    name={['signing', 'key']}
+                    tooltip="GPG 填写密钥 ID（留空使用默认密钥）；SSH 填写私钥或公钥文件路径"
+                  >
+                    <Input placeholder="密钥 ID 或密钥文件路径" />
+                  </Form.Item>
+                  <Form.Item
+                    label="签名程序"
+                    name={['signing', 'program']}
+                    tooltip="留空时 GPG 使用 gpg，SSH 使用 ssh-keygen"
+                  >
+                    <Input placeholder="gpg / ssh-keygen" />
+                  </Form.Item>
+                  <Form.Item>
+                    <Button onClick={handleTestSigning}>测试签名</Button>
+                  </Form.Item>
+                </>
+              )
+            }
+          </Form.Item>
+
           <Form.Item
             label="启用 GitHub Pages"
             name="enableGithubPages"
@@ -384,6 +657,21 @@ export default function Settings() {
             <Switch />
           </Form.Item>
 
+          <Form.Item noStyle shouldUpdate={(prev, cur) => prev.enableGithubPages !== cur.enableGithubPages}>
+            {({ getFieldValue }) =>
+              getFieldValue('enableGithubPages') && (
+                <Form.Item
+                  label="发布时排除笔记"
+                  name="pagesExcludeNotes"
+                  valuePropName="checked"
+                  tooltip="生成的网站不包含「笔记」区域"
+                >
+                  <Switch />
+                </Form.Item>
+              )
+            }
+          </Form.Item>
+
           {editMode && (
             <Form.Item>
               <Button type="primary" htmlType="submit" icon={<SaveOutlined />} loading={loading} block>
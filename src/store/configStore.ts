@@ -5,10 +5,178 @@ export interface Config {
   localPath: string;
   userName: string;
   userEmail: string;
+  deviceName?: string;
   remoteUrl?: string;
+  localOnly?: boolean;
   token?: string;
-  gitProvider: 'github' | 'gitlab' | 'gitee';
+  tokenRef?: string;
+  gitProvider: 'github' | 'gitlab' | 'gitee' | 'gitea' | 'forgejo';
+  providerBaseUrl?: string;
+  customProviders?: CustomProvider[];
   enableGithubPages: boolean;
+  pagesExcludeNotes?: boolean;
+  runGitHooks?: boolean;
+  signing?: SigningConfig;
+  commitTemplates?: CommitTemplates;
+  trashRetentionDays?: number;
+  maintenanceIntervalDays?: number;
+  gitTimeoutSecs?: number;
+  gitLocalTimeoutSecs?: number;
+  remotes?: RemoteConfig[];
+  sshKeyPath?: string;
+  sshPassphrase?: string;
+  sshPassphraseRef?: string;
+  sshKnownHostsPath?: string;
+}
+
+export interface SigningConfig {
+  enabled: boolean;
+  format: 'gpg' | 'ssh';
+  key?: string;
+  program?: string;
+}
+
+export interface CommitTemplates {
+  style: 'zh' | 'en' | 'conventional';
+  /** 按操作覆盖的模板，键为操作名（update、upload_attachment 等） */
+  overrides?: Record<string, string>;
+}
+
+export interface TaskDiff {
+  added: string[];
+  removed: string[];
+  completed: string[];
+  reopened: string[];
+}
+
+export interface DayChange {
+  date: string;
+  filepath: string;
+  tasks: TaskDiff;
+}
+
+export interface IncomingChanges {
+  from: string | null;
+  to: string | null;
+  commits: number;
+  authors: string[];
+  devices: string[];
+  days: DayChange[];
+}
+
+/** 推送结果：推送成功后发布站点失败时 pagesError 为失败原因 */
+export interface PushResult {
+  pagesError: string | null;
+}
+
+export interface DeviceActivity {
+  device: string | null;
+  commits: number;
+  activeDays: number;
+  authors: string[];
+  firstCommit: string;
+  lastCommit: string;
+}
+
+/** git_pull 的错误：远程历史需要用户选择处理方式时附带 divergence */
+export interface PullError {
+  message: string;
+  divergence: Divergence | null;
+}
+
+export interface SigningError {
+  kind: 'program_not_found' | 'key_missing' | 'signing_failed' | 'timeout';
+  program: string;
+  message: string;
+}
+
+/** 创建提交的命令的错误：签名失败时为 SigningError，其余为错误信息 */
+export type CommitError = SigningError | string;
+
+function isSigningError(error: unknown): error is SigningError {
+  return !!error && typeof error === 'object' && 'kind' in error && 'program' in error;
+}
+
+/** 错误的文字描述，签名失败时附带原因提示 */
+export function formatError(error: unknown): string {
+  if (!isSigningError(error)) {
+    return String(error);
+  }
+  const hint =
+    error.kind === 'program_not_found'
+      ? `找不到签名程序 ${error.program}`
+      : error.kind === 'key_missing'
+        ? '找不到签名密钥'
+        : error.kind === 'timeout'
+          ? '签名超时'
+          : '签名失败';
+  return `${hint}: ${error.message}`;
+}
+
+export interface CustomProvider {
+  name: string;
+  kind: 'github' | 'gitlab' | 'gitea' | 'forgejo';
+  baseUrl: string;
+  urlPatterns?: string[];
+}
+
+export interface AdoptRename {
+  from: string;
+  to: string;
+}
+
+export interface FolderScan {
+  matched: string[];
+  convertible: AdoptRename[];
+  conflicts: AdoptRename[];
+  unmatched: string[];
+  attachments: string[];
+  isGitRepo: boolean;
+}
+
+export interface AdoptResult {
+  importedFiles: number;
+  dayFiles: number;
+  renamed: AdoptRename[];
+  commit: string | null;
+}
+
+export interface Divergence {
+  kind: 'up_to_date' | 'ahead' | 'behind' | 'diverged' | 'rewritten' | 'unrelated' | 'no_remote_branch';
+  branch: string;
+  local: string | null;
+  remote: string | null;
+  ahead: number;
+  behind: number;
+  dropped: number;
+}
+
+export type DivergenceChoice = 'force_push' | 'take_remote' | 'merge_unrelated';
+
+export interface DivergenceResolution {
+  choice: DivergenceChoice;
+  backupRefs: string[];
+  incoming: IncomingChanges;
+}
+
+export interface AttachResult {
+  branch: string;
+  outcome: 'pushed' | 'up_to_date' | 'fast_forward' | 'merged' | 'merged_unrelated';
+  incoming: IncomingChanges;
+}
+
+/** 是否启用同步：非仅本地模式且配置了主远程仓库 */
+export function syncEnabled(config: Config | null): boolean {
+  if (!config || config.localOnly) {
+    return false;
+  }
+  return !!config.remoteUrl || !!config.remotes?.some((r) => r.role === 'primary' && r.url);
+}
+
+export interface RemoteConfig {
+  name: string;
+  url: string;
+  role: 'primary' | 'mirror';
 }
 
 interface ConfigState {
@@ -19,6 +187,8 @@ interface ConfigState {
   loadConfig: () => Promise<void>;
   saveConfig: (config: Config) => Promise<void>;
   initGit: (config: Config) => Promise<void>;
+  adoptFolder: (config: Config, convert: boolean) => Promise<AdoptResult>;
+  attachRemote: (remoteUrl: string, token?: string, gitProvider?: string) => Promise<AttachResult>;
   notifySyncComplete: () => void;
 }
 
@@ -73,6 +243,26 @@ export const useConfigStore = create<ConfigState>((set) => ({
     }
   },
 
+  adoptFolder: async (config: Config, convert: boolean) => {
+    try {
+      set({ gitReady: false });
+      const result = await invoke<AdoptResult>('adopt_folder', { config, convert });
+      set({ config, isConfigured: true, gitReady: true });
+      return result;
+    } catch (error) {
+      console.error('导入文件夹失败:', error);
+      set({ gitReady: true });
+      throw error;
+    }
+  },
+
+  attachRemote: async (remoteUrl: string, token?: string, gitProvider?: string) => {
+    const result = await invoke<AttachResult>('attach_remote', { remoteUrl, token, gitProvider });
+    const config = await invoke<Config | null>('load_config');
+    set((state) => ({ config, syncVersion: state.syncVersion + 1 }));
+    return result;
+  },
+
   notifySyncComplete: () => {
     set((state) => ({ syncVersion: state.syncVersion + 1 }));
   },